@@ -0,0 +1,34 @@
+#version 330 core
+
+in vec2 TexCoord;
+
+out vec4 Color;
+
+uniform sampler2D u_texture;
+uniform vec2 u_texel_size;
+uniform float u_threshold = 1.0;
+uniform float u_intensity = 0.6;
+
+vec3 bright(vec2 uv)
+{
+    vec3 c = texture(u_texture, uv).rgb;
+    float luma = dot(c, vec3(0.2126, 0.7152, 0.0722));
+    return c * max(luma - u_threshold, 0.0) / max(luma, 0.0001);
+}
+
+void main()
+{
+    // 9x9 gaussian over the bright parts, sampled every second texel
+    const float weights[5] = float[](0.227, 0.194, 0.121, 0.054, 0.016);
+
+    vec3 glow = vec3(0.0);
+    for (int y = -4; y <= 4; y++) {
+        for (int x = -4; x <= 4; x++) {
+            vec2 offset = vec2(x, y) * u_texel_size * 2.0;
+            glow += bright(TexCoord + offset) * weights[abs(x)] * weights[abs(y)];
+        }
+    }
+
+    vec4 base = texture(u_texture, TexCoord);
+    Color = vec4(base.rgb + glow * u_intensity, base.a);
+}
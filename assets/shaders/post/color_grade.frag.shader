@@ -0,0 +1,33 @@
+#version 330 core
+
+in vec2 TexCoord;
+
+out vec4 Color;
+
+uniform sampler2D u_texture;
+// 2D strip of a 3D lookup table: `size` square slices along x, blue picks the
+// slice; load it with flip_vertically(false) so green grows downwards
+uniform sampler2D u_lut;
+uniform float u_lut_size = 16.0;
+uniform float u_strength = 1.0;
+
+vec3 lookup(vec3 c)
+{
+    float size = u_lut_size;
+    float slice = c.b * (size - 1.0);
+    float slice0 = floor(slice);
+    float slice1 = min(slice0 + 1.0, size - 1.0);
+
+    // sample texel centers so neighbouring slices never bleed in
+    vec2 uv = (c.rg * (size - 1.0) + 0.5) / vec2(size * size, size);
+    vec3 a = texture(u_lut, uv + vec2(slice0 / size, 0.0)).rgb;
+    vec3 b = texture(u_lut, uv + vec2(slice1 / size, 0.0)).rgb;
+    return mix(a, b, slice - slice0);
+}
+
+void main()
+{
+    vec4 c = texture(u_texture, TexCoord);
+    vec3 graded = lookup(clamp(c.rgb, 0.0, 1.0));
+    Color = vec4(mix(c.rgb, graded, u_strength), c.a);
+}
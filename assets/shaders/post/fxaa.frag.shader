@@ -0,0 +1,46 @@
+#version 330 core
+
+in vec2 TexCoord;
+
+out vec4 Color;
+
+uniform sampler2D u_texture;
+uniform vec2 u_texel_size;
+
+const float SPAN_MAX = 8.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 c)
+{
+    return dot(c, vec3(0.299, 0.587, 0.114));
+}
+
+// expects tone mapped input, so run it after tonemap
+void main()
+{
+    float nw = luma(texture(u_texture, TexCoord + vec2(-1.0, -1.0) * u_texel_size).rgb);
+    float ne = luma(texture(u_texture, TexCoord + vec2( 1.0, -1.0) * u_texel_size).rgb);
+    float sw = luma(texture(u_texture, TexCoord + vec2(-1.0,  1.0) * u_texel_size).rgb);
+    float se = luma(texture(u_texture, TexCoord + vec2( 1.0,  1.0) * u_texel_size).rgb);
+    vec4 center = texture(u_texture, TexCoord);
+    float m = luma(center.rgb);
+
+    float luma_min = min(m, min(min(nw, ne), min(sw, se)));
+    float luma_max = max(m, max(max(nw, ne), max(sw, se)));
+
+    vec2 dir = vec2(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+    float reduce = max((nw + ne + sw + se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * u_texel_size;
+
+    vec3 a = 0.5 * (
+        texture(u_texture, TexCoord + dir * (1.0 / 3.0 - 0.5)).rgb +
+        texture(u_texture, TexCoord + dir * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 b = a * 0.5 + 0.25 * (
+        texture(u_texture, TexCoord - dir * 0.5).rgb +
+        texture(u_texture, TexCoord + dir * 0.5).rgb);
+
+    float luma_b = luma(b);
+    Color = vec4((luma_b < luma_min || luma_b > luma_max) ? a : b, center.a);
+}
@@ -0,0 +1,20 @@
+#version 330 core
+
+in vec2 TexCoord;
+
+out vec4 Color;
+
+uniform sampler2D u_texture;
+uniform float u_exposure = 1.0;
+
+// ACES filmic fit by Krzysztof Narkowicz
+vec3 aces(vec3 x)
+{
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main()
+{
+    vec4 hdr = texture(u_texture, TexCoord);
+    Color = vec4(aces(hdr.rgb * u_exposure), hdr.a);
+}
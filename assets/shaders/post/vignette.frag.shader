@@ -0,0 +1,18 @@
+#version 330 core
+
+in vec2 TexCoord;
+
+out vec4 Color;
+
+uniform sampler2D u_texture;
+uniform float u_radius = 0.75;
+uniform float u_softness = 0.45;
+uniform float u_strength = 0.5;
+
+void main()
+{
+    vec4 c = texture(u_texture, TexCoord);
+    float dist = length(TexCoord - 0.5) * 1.41421356;
+    float shade = smoothstep(u_radius, u_radius - u_softness, dist);
+    Color = vec4(c.rgb * mix(1.0, shade, u_strength), c.a);
+}
@@ -8,9 +8,11 @@ out VS_OUTPUT {
 } OUT;
 
 uniform mat4 ModelMatrix = mat4(1.0);
+uniform mat4 View = mat4(1.0);
+uniform mat4 Projection = mat4(1.0);
 
 void main()
 {
-    gl_Position = ModelMatrix * vec4(Position, 1.0);
+    gl_Position = Projection * View * ModelMatrix * vec4(Position, 1.0);
     OUT.Color = Color.xyz;
 }
\ No newline at end of file
@@ -28,6 +28,13 @@ fn generate_impl(ast: &syn::DeriveInput) -> quote::Tokens {
                 #(#fields_vertex_attrib_pointer)*
             }
         }
+
+        impl #generics ::render_gl::data::VertexAttribPointers for #ident #generics #where_clause {
+            fn vertex_attrib_pointers(gl: &::gl::Gl) {
+                // resolves to the inherent function above
+                #ident::vertex_attrib_pointers(gl)
+            }
+        }
     }
 }
 
@@ -0,0 +1,1107 @@
+//! glTF 2.0 import from `.gltf` files, with external or embedded buffers
+//! and images, and from binary `.glb` files.
+//!
+//! Everything is decoded to CPU memory first; meshes and textures are
+//! uploaded on request, so a model can be inspected or edited before it
+//! touches the GPU.
+
+use base64;
+use gl;
+use gltf;
+use image;
+use nalgebra as na;
+
+use geometry::primitives::MeshData;
+use geometry::vertex::{MeshVertex, SkinnedMeshVertex};
+use render_gl::{Color, ImageData, MagFilter, Mesh, MinFilter, Primitive, Texture, TextureBuilder, Wrap};
+use resources::{self, Resources};
+use scene::Transform;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Unable to load resource {}", name)]
+    ResourceLoad {
+        name: String,
+        #[cause] inner: resources::Error
+    },
+
+    #[fail(display = "Invalid glTF {}", name)]
+    Gltf {
+        name: String,
+        #[cause] inner: gltf::Error
+    },
+
+    #[fail(display = "{} references a binary chunk, but is not a .glb with one", name)]
+    MissingBlob {
+        name: String
+    },
+
+    #[fail(display = "Invalid base64 data URI in {}", name)]
+    DataUri {
+        name: String,
+        #[cause] inner: base64::DecodeError
+    },
+
+    #[fail(display = "Unable to decode image {} of {}", image, name)]
+    ImageDecode {
+        name: String,
+        image: usize,
+        #[cause] inner: image::ImageError
+    },
+
+    #[fail(display = "Mesh {} of {} has a primitive without positions", mesh, name)]
+    MissingPositions {
+        name: String,
+        mesh: usize
+    },
+
+    #[fail(display = "Buffer view {} of {} ends at byte {}, but its buffer has only {}", view, name, end, buffer_length)]
+    BufferViewOutOfRange {
+        name: String,
+        view: usize,
+        end: usize,
+        buffer_length: usize
+    },
+
+    #[fail(display = "Accessor {} of {} reaches past the end of its buffer view", accessor, name)]
+    AccessorOutOfRange {
+        name: String,
+        accessor: usize
+    },
+
+    #[fail(display = "Mesh {} of {} has {} {} values for {} positions", mesh, name, count, attribute, expected)]
+    AttributeCount {
+        name: String,
+        mesh: usize,
+        attribute: &'static str,
+        count: usize,
+        expected: usize
+    },
+
+    #[fail(display = "Mesh {} of {} uses vertex {}, but has only {}", mesh, name, index, vertices)]
+    IndexOutOfRange {
+        name: String,
+        mesh: usize,
+        index: u32,
+        vertices: usize
+    },
+
+    #[fail(display = "Channel {} of animation {} in {} has {} output values, expected {}", channel, animation, name, count, expected)]
+    AnimationValueCount {
+        name: String,
+        animation: usize,
+        channel: usize,
+        count: usize,
+        expected: usize
+    },
+}
+
+/// Texture used by a material, and the UV set it is sampled with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TextureRef {
+    /// Index into `GltfModel::textures`.
+    pub texture: usize,
+    pub tex_coord: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    /// Fully transparent below `cutoff`, opaque otherwise.
+    Mask { cutoff: f32 },
+    Blend,
+}
+
+/// PBR metallic-roughness material. Factors are linear; base color and
+/// emissive textures are sRGB encoded, all other textures are linear.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfMaterial {
+    pub name: Option<String>,
+    pub base_color: Color,
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Roughness in the green channel, metalness in blue.
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    /// Ambient occlusion in the red channel.
+    pub occlusion_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive: Color,
+    pub emissive_texture: Option<TextureRef>,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+}
+
+impl Default for GltfMaterial {
+    /// The material the specification prescribes for primitives without one.
+    fn default() -> GltfMaterial {
+        GltfMaterial {
+            name: None,
+            base_color: Color::linear(1.0, 1.0, 1.0, 1.0),
+            base_color_texture: None,
+            metallic: 1.0,
+            roughness: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive: Color::linear(0.0, 0.0, 0.0, 1.0),
+            emissive_texture: None,
+            alpha_mode: AlphaMode::Opaque,
+            double_sided: false,
+        }
+    }
+}
+
+/// An image with the sampling parameters it is used with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfTexture {
+    pub name: Option<String>,
+    /// Index into `GltfModel::images`.
+    pub image: usize,
+    pub min_filter: MinFilter,
+    pub mag_filter: MagFilter,
+    pub wrap_s: Wrap,
+    pub wrap_t: Wrap,
+}
+
+/// Decoded image, first row at the top, matching glTF texture coordinates.
+#[derive(Clone, Debug)]
+pub struct GltfImage {
+    pub name: Option<String>,
+    pub data: ImageData,
+}
+
+/// Vertices drawn with one material. `joints` and `weights` hold one entry
+/// per vertex for skinned meshes and are empty otherwise.
+#[derive(Clone, Debug)]
+pub struct GltfPrimitive {
+    /// Index into `GltfModel::materials`; `None` means `GltfMaterial::default()`.
+    pub material: Option<usize>,
+    pub mode: Primitive,
+    pub data: MeshData,
+    pub joints: Vec<[u16; 4]>,
+    pub weights: Vec<[f32; 4]>,
+}
+
+impl GltfPrimitive {
+    pub fn is_skinned(&self) -> bool {
+        !self.joints.is_empty() && self.joints.len() == self.weights.len()
+    }
+
+    pub fn upload(&self, gl: &gl::Gl) -> Mesh<MeshVertex> {
+        Mesh::indexed(gl, &self.data.vertices, &self.data.indices, self.mode)
+    }
+
+    /// `None` unless the primitive has joints and weights.
+    pub fn upload_skinned(&self, gl: &gl::Gl) -> Option<Mesh<SkinnedMeshVertex>> {
+        if !self.is_skinned() {
+            return None;
+        }
+
+        let vertices = self.data.vertices.iter()
+            .zip(self.joints.iter().zip(self.weights.iter()))
+            .map(|(v, (j, w))| SkinnedMeshVertex {
+                pos: v.pos,
+                normal: v.normal,
+                tangent: v.tangent,
+                uv: v.uv,
+                joints: (j[0], j[1], j[2], j[3]).into(),
+                weights: (w[0], w[1], w[2], w[3]).into(),
+            })
+            .collect::<Vec<_>>();
+
+        Some(Mesh::indexed(gl, &vertices, &self.data.indices, self.mode))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+/// Node of the hierarchy; animations replace parts of its transform.
+#[derive(Clone, Debug)]
+pub struct GltfNode {
+    pub name: Option<String>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// Relative to the parent node.
+    pub transform: Transform,
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+    pub skin: Option<usize>,
+    /// Morph target weights, animated by `Property::Weights` channels.
+    pub weights: Vec<f32>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GltfProjection {
+    /// Without `zfar` the far plane is at infinity; without `aspect_ratio`
+    /// the aspect of the viewport is used.
+    Perspective {
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+impl GltfProjection {
+    /// Projection matrix as given in the specification; `aspect` is the
+    /// viewport's width over height.
+    pub fn matrix(&self, aspect: f32) -> na::Matrix4<f32> {
+        match *self {
+            GltfProjection::Perspective { yfov, aspect_ratio, znear, zfar } => {
+                let a = aspect_ratio.unwrap_or(aspect);
+                let f = 1.0 / (yfov * 0.5).tan();
+
+                let (m22, m23) = match zfar {
+                    Some(zfar) => ((zfar + znear) / (znear - zfar), 2.0 * zfar * znear / (znear - zfar)),
+                    None => (-1.0, -2.0 * znear),
+                };
+
+                na::Matrix4::new(
+                    f / a, 0.0, 0.0, 0.0,
+                    0.0, f, 0.0, 0.0,
+                    0.0, 0.0, m22, m23,
+                    0.0, 0.0, -1.0, 0.0,
+                )
+            },
+            GltfProjection::Orthographic { xmag, ymag, znear, zfar } => na::Matrix4::new(
+                1.0 / xmag, 0.0, 0.0, 0.0,
+                0.0, 1.0 / ymag, 0.0, 0.0,
+                0.0, 0.0, 2.0 / (znear - zfar), (zfar + znear) / (znear - zfar),
+                0.0, 0.0, 0.0, 1.0,
+            ),
+        }
+    }
+}
+
+/// Camera looking down its node's -Z axis with +Y up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfCamera {
+    pub name: Option<String>,
+    pub projection: GltfProjection,
+}
+
+#[derive(Clone, Debug)]
+pub struct GltfSkin {
+    pub name: Option<String>,
+    /// Node indices; `SkinnedMeshVertex::joints` index into this list.
+    pub joints: Vec<usize>,
+    /// One per joint; identity if the file has none.
+    pub inverse_bind_matrices: Vec<na::Matrix4<f32>>,
+    pub skeleton: Option<usize>,
+}
+
+impl GltfSkin {
+    /// Matrices for the vertex shader, which move a vertex from the bind
+    /// pose to the current pose, in the space of the node `mesh_node` that
+    /// draws the skinned mesh. `world` is `GltfModel::world_matrices`.
+    pub fn joint_matrices(&self, world: &[na::Matrix4<f32>], mesh_node: usize) -> Vec<na::Matrix4<f32>> {
+        let to_mesh = world[mesh_node].try_inverse().unwrap_or_else(na::Matrix4::identity);
+
+        self.joints.iter()
+            .zip(self.inverse_bind_matrices.iter())
+            .map(|(&joint, inverse_bind)| to_mesh * world[joint] * inverse_bind)
+            .collect()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Property {
+    Translation,
+    Rotation,
+    Scale,
+    Weights,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Step,
+    Linear,
+    /// Every key stores an in-tangent, the value and an out-tangent.
+    CubicSpline,
+}
+
+/// Keyframes animating one property of one node.
+#[derive(Clone, Debug)]
+pub struct GltfChannel {
+    pub node: usize,
+    pub property: Property,
+    pub interpolation: Interpolation,
+    /// Key times in seconds, increasing.
+    pub times: Vec<f32>,
+    /// Key values, flattened: xyz for translation and scale, xyzw for
+    /// rotation and one weight per morph target for weights.
+    pub values: Vec<f32>,
+}
+
+impl GltfChannel {
+    /// Number of floats in one value.
+    pub fn components(&self) -> usize {
+        match self.property {
+            Property::Translation | Property::Scale => 3,
+            Property::Rotation => 4,
+            Property::Weights => {
+                let per_key = if self.interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+                self.values.len() / (self.times.len() * per_key).max(1)
+            },
+        }
+    }
+
+    /// Value at `time`, clamped to the first and last key.
+    pub fn sample(&self, time: f32) -> Vec<f32> {
+        let n = self.components();
+        let cubic = self.interpolation == Interpolation::CubicSpline;
+        let element = |key: usize, part: usize, c: usize| if cubic {
+            self.values[(key * 3 + part) * n + c]
+        } else {
+            self.values[key * n + c]
+        };
+        let value = |key: usize| (0..n).map(|c| element(key, 1, c)).collect::<Vec<_>>();
+
+        let last = match self.times.len() {
+            0 => return vec![0.0; n],
+            len => len - 1,
+        };
+        if time <= self.times[0] {
+            return value(0);
+        }
+        if time >= self.times[last] {
+            return value(last);
+        }
+
+        let next = self.times.iter().position(|&t| t > time).unwrap_or(last);
+        let prev = next - 1;
+        let dt = self.times[next] - self.times[prev];
+        let t = (time - self.times[prev]) / dt;
+
+        let mut result = match self.interpolation {
+            Interpolation::Step => value(prev),
+            Interpolation::Linear => if self.property == Property::Rotation {
+                let (a, b) = (value(prev), value(next));
+                // q and -q are the same rotation; take the shorter way round
+                let sign = if (0..4).map(|c| a[c] * b[c]).sum::<f32>() < 0.0 { -1.0 } else { 1.0 };
+                let q0 = na::UnitQuaternion::from_quaternion(na::Quaternion::new(a[3], a[0], a[1], a[2]));
+                let q1 = na::UnitQuaternion::from_quaternion(na::Quaternion::new(b[3], b[0], b[1], b[2]) * sign);
+                let q = q0.try_slerp(&q1, t, 1e-6).unwrap_or_else(|| q0.nlerp(&q1, t));
+                vec![q.coords.x, q.coords.y, q.coords.z, q.coords.w]
+            } else {
+                (0..n).map(|c| {
+                    let (a, b) = (element(prev, 1, c), element(next, 1, c));
+                    a + (b - a) * t
+                }).collect()
+            },
+            Interpolation::CubicSpline => {
+                let (t2, t3) = (t * t, t * t * t);
+                (0..n).map(|c| {
+                    let v0 = element(prev, 1, c);
+                    let out_tangent = element(prev, 2, c);
+                    let in_tangent = element(next, 0, c);
+                    let v1 = element(next, 1, c);
+
+                    (2.0 * t3 - 3.0 * t2 + 1.0) * v0
+                        + (t3 - 2.0 * t2 + t) * dt * out_tangent
+                        + (-2.0 * t3 + 3.0 * t2) * v1
+                        + (t3 - t2) * dt * in_tangent
+                }).collect()
+            },
+        };
+
+        if self.property == Property::Rotation {
+            let length = result.iter().map(|c| c * c).sum::<f32>().sqrt();
+            if length > 0.0 {
+                for c in result.iter_mut() {
+                    *c /= length;
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GltfAnimation {
+    pub name: Option<String>,
+    pub channels: Vec<GltfChannel>,
+    /// Time of the last key over all channels, in seconds.
+    pub duration: f32,
+}
+
+impl GltfAnimation {
+    /// Poses `nodes` at `time`; properties without a channel keep their value.
+    pub fn apply(&self, time: f32, nodes: &mut [GltfNode]) {
+        for channel in self.channels.iter() {
+            let value = channel.sample(time);
+            let node = match nodes.get_mut(channel.node) {
+                Some(node) => node,
+                None => continue,
+            };
+
+            match channel.property {
+                Property::Translation => node.transform.translation = na::Vector3::new(value[0], value[1], value[2]),
+                Property::Rotation => node.transform.rotation = na::UnitQuaternion::from_quaternion(
+                    na::Quaternion::new(value[3], value[0], value[1], value[2])
+                ),
+                Property::Scale => node.transform.scale = na::Vector3::new(value[0], value[1], value[2]),
+                Property::Weights => node.weights = value,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GltfScene {
+    pub name: Option<String>,
+    /// Root nodes.
+    pub nodes: Vec<usize>,
+}
+
+/// Everything in a glTF asset, with cross references as indices into the
+/// vectors of this struct, as in the file.
+#[derive(Clone, Debug)]
+pub struct GltfModel {
+    pub scenes: Vec<GltfScene>,
+    pub default_scene: Option<usize>,
+    pub nodes: Vec<GltfNode>,
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    pub textures: Vec<GltfTexture>,
+    pub images: Vec<GltfImage>,
+    pub cameras: Vec<GltfCamera>,
+    pub skins: Vec<GltfSkin>,
+    pub animations: Vec<GltfAnimation>,
+}
+
+impl GltfModel {
+    /// Loads `.gltf` or `.glb`; external buffers and images are resolved
+    /// relative to `name`.
+    pub fn from_res(res: &Resources, name: &str) -> Result<GltfModel, Error> {
+        let bytes = load(res, name)?;
+        let gltf::Gltf { document, blob } = gltf::Gltf::from_slice(&bytes)
+            .map_err(|e| Error::Gltf {
+                name: name.into(),
+                inner: e
+            })?;
+
+        let buffers = load_buffers(res, name, &document, blob)?;
+        // the accessor readers slice the buffers without checking
+        check_ranges(name, &document, &buffers)?;
+        let get_buffer = |buffer: gltf::Buffer| buffers.get(buffer.index()).map(|data| &data[..]);
+
+        let images = document.images()
+            .map(|image| load_image(res, name, &image, &buffers))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let textures = document.textures().map(|texture| {
+            let sampler = texture.sampler();
+            GltfTexture {
+                name: texture.name().map(Into::into),
+                image: texture.source().index(),
+                min_filter: sampler.min_filter().map(min_filter).unwrap_or(MinFilter::LinearMipmapLinear),
+                mag_filter: sampler.mag_filter().map(mag_filter).unwrap_or(MagFilter::Linear),
+                wrap_s: wrap(sampler.wrap_s()),
+                wrap_t: wrap(sampler.wrap_t()),
+            }
+        }).collect();
+
+        let materials = document.materials().map(|material| material_from(&material)).collect();
+
+        let mut meshes = Vec::new();
+        for mesh in document.meshes() {
+            let mut primitives = Vec::new();
+            for primitive in mesh.primitives() {
+                let reader = primitive.reader(get_buffer);
+                let mode = primitive_mode(primitive.mode());
+
+                let positions = reader.read_positions()
+                    .ok_or_else(|| Error::MissingPositions {
+                        name: name.into(),
+                        mesh: mesh.index()
+                    })?
+                    .collect::<Vec<_>>();
+                let normals = reader.read_normals().map(|normals| normals.collect::<Vec<_>>());
+                let tangents = reader.read_tangents().map(|tangents| tangents.collect::<Vec<_>>());
+                let uvs = reader.read_tex_coords(0).map(|uvs| uvs.into_f32().collect::<Vec<_>>());
+                let joints = reader.read_joints(0)
+                    .map(|joints| joints.into_u16().collect::<Vec<_>>())
+                    .unwrap_or_default();
+                let weights = reader.read_weights(0)
+                    .map(|weights| weights.into_f32().collect::<Vec<_>>())
+                    .unwrap_or_default();
+
+                let check_count = |attribute: &'static str, count: Option<usize>| match count {
+                    Some(count) if count != positions.len() => Err(Error::AttributeCount {
+                        name: name.into(),
+                        mesh: mesh.index(),
+                        attribute,
+                        count,
+                        expected: positions.len()
+                    }),
+                    _ => Ok(()),
+                };
+                check_count("normal", normals.as_ref().map(Vec::len))?;
+                check_count("tangent", tangents.as_ref().map(Vec::len))?;
+                check_count("texture coordinate", uvs.as_ref().map(Vec::len))?;
+                check_count("joint", Some(joints.len()).filter(|&count| count > 0))?;
+                check_count("weight", Some(weights.len()).filter(|&count| count > 0))?;
+
+                let mut data = MeshData::default();
+                for (i, p) in positions.iter().enumerate() {
+                    let n = normals.as_ref().map(|normals| normals[i]).unwrap_or([0.0, 0.0, 0.0]);
+                    let t = tangents.as_ref().map(|tangents| tangents[i]).unwrap_or([1.0, 0.0, 0.0, 1.0]);
+                    let uv = uvs.as_ref().map(|uvs| uvs[i]).unwrap_or([0.0, 0.0]);
+
+                    data.vertices.push(MeshVertex {
+                        pos: (p[0], p[1], p[2]).into(),
+                        normal: (n[0], n[1], n[2]).into(),
+                        tangent: (t[0], t[1], t[2], t[3]).into(),
+                        uv: (uv[0], uv[1]).into(),
+                    });
+                }
+                data.indices = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().collect(),
+                    None => (0..positions.len() as u32).collect(),
+                };
+                if let Some(&index) = data.indices.iter().find(|&&index| index as usize >= positions.len()) {
+                    return Err(Error::IndexOutOfRange {
+                        name: name.into(),
+                        mesh: mesh.index(),
+                        index,
+                        vertices: positions.len()
+                    });
+                }
+
+                let mut gltf_primitive = GltfPrimitive {
+                    material: primitive.material().index(),
+                    mode,
+                    data,
+                    joints,
+                    weights,
+                };
+
+                if mode == Primitive::Triangles {
+                    if normals.is_none() {
+                        flat_normals(&mut gltf_primitive);
+                    }
+                    if tangents.is_none() && uvs.is_some() {
+                        gltf_primitive.data.compute_tangents();
+                    }
+                }
+
+                primitives.push(gltf_primitive);
+            }
+
+            meshes.push(GltfMesh {
+                name: mesh.name().map(Into::into),
+                primitives,
+            });
+        }
+
+        let mut nodes = document.nodes().map(|node| {
+            let (t, r, s) = node.transform().decomposed();
+            let weights = node.weights()
+                .or_else(|| node.mesh().and_then(|mesh| mesh.weights()))
+                .map(|weights| weights.to_vec())
+                .unwrap_or_default();
+
+            GltfNode {
+                name: node.name().map(Into::into),
+                parent: None,
+                children: node.children().map(|child| child.index()).collect(),
+                transform: Transform::new(
+                    na::Vector3::new(t[0], t[1], t[2]),
+                    na::UnitQuaternion::from_quaternion(na::Quaternion::new(r[3], r[0], r[1], r[2])),
+                    na::Vector3::new(s[0], s[1], s[2]),
+                ),
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                camera: node.camera().map(|camera| camera.index()),
+                skin: node.skin().map(|skin| skin.index()),
+                weights,
+            }
+        }).collect::<Vec<_>>();
+
+        for parent in 0..nodes.len() {
+            for child in nodes[parent].children.clone() {
+                nodes[child].parent = Some(parent);
+            }
+        }
+
+        let cameras = document.cameras().map(|camera| GltfCamera {
+            name: camera.name().map(Into::into),
+            projection: match camera.projection() {
+                gltf::camera::Projection::Perspective(p) => GltfProjection::Perspective {
+                    yfov: p.yfov(),
+                    aspect_ratio: p.aspect_ratio(),
+                    znear: p.znear(),
+                    zfar: p.zfar(),
+                },
+                gltf::camera::Projection::Orthographic(o) => GltfProjection::Orthographic {
+                    xmag: o.xmag(),
+                    ymag: o.ymag(),
+                    znear: o.znear(),
+                    zfar: o.zfar(),
+                },
+            },
+        }).collect();
+
+        let skins = document.skins().map(|skin| {
+            let joints = skin.joints().map(|joint| joint.index()).collect::<Vec<_>>();
+            let inverse_bind_matrices = skin.reader(get_buffer)
+                .read_inverse_bind_matrices()
+                .map(|matrices| matrices.map(|m| matrix_from_columns(&m)).collect())
+                .unwrap_or_else(|| vec![na::Matrix4::identity(); joints.len()]);
+
+            GltfSkin {
+                name: skin.name().map(Into::into),
+                joints,
+                inverse_bind_matrices,
+                skeleton: skin.skeleton().map(|node| node.index()),
+            }
+        }).collect();
+
+        let mut animations = Vec::new();
+        for animation in document.animations() {
+            let mut channels = Vec::new();
+            for (index, channel) in animation.channels().enumerate() {
+                let reader = channel.reader(get_buffer);
+                let (times, outputs) = match (reader.read_inputs(), reader.read_outputs()) {
+                    (Some(times), Some(outputs)) => (times.collect::<Vec<_>>(), outputs),
+                    _ => continue,
+                };
+                let (property, values): (_, Vec<f32>) = match outputs {
+                    gltf::animation::util::ReadOutputs::Translations(values) =>
+                        (Property::Translation, values.flat_map(|v| v.to_vec()).collect()),
+                    gltf::animation::util::ReadOutputs::Rotations(values) =>
+                        (Property::Rotation, values.into_f32().flat_map(|v| v.to_vec()).collect()),
+                    gltf::animation::util::ReadOutputs::Scales(values) =>
+                        (Property::Scale, values.flat_map(|v| v.to_vec()).collect()),
+                    gltf::animation::util::ReadOutputs::MorphTargetWeights(values) =>
+                        (Property::Weights, values.into_f32().collect()),
+                };
+
+                let interpolation = match channel.sampler().interpolation() {
+                    gltf::animation::Interpolation::Step => Interpolation::Step,
+                    gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                    gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                };
+
+                // cubic splines store an in-tangent, value and out-tangent per key
+                let node = channel.target().node();
+                let components = match property {
+                    Property::Translation | Property::Scale => 3,
+                    Property::Rotation => 4,
+                    Property::Weights => node.mesh()
+                        .and_then(|mesh| mesh.primitives().next())
+                        .map_or(0, |primitive| primitive.morph_targets().len()),
+                };
+                let per_key = if interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+                let expected = times.len() * components * per_key;
+                if values.len() != expected {
+                    return Err(Error::AnimationValueCount {
+                        name: name.into(),
+                        animation: animation.index(),
+                        channel: index,
+                        count: values.len(),
+                        expected
+                    });
+                }
+
+                channels.push(GltfChannel {
+                    node: node.index(),
+                    property,
+                    interpolation,
+                    times,
+                    values,
+                });
+            }
+
+            let duration = channels.iter()
+                .filter_map(|channel| channel.times.last().cloned())
+                .fold(0.0, f32::max);
+
+            animations.push(GltfAnimation {
+                name: animation.name().map(Into::into),
+                channels,
+                duration,
+            });
+        }
+
+        let scenes = document.scenes().map(|scene| GltfScene {
+            name: scene.name().map(Into::into),
+            nodes: scene.nodes().map(|node| node.index()).collect(),
+        }).collect();
+
+        Ok(GltfModel {
+            scenes,
+            default_scene: document.default_scene().map(|scene| scene.index()),
+            nodes,
+            meshes,
+            materials,
+            textures,
+            images,
+            cameras,
+            skins,
+            animations,
+        })
+    }
+
+    /// The default scene, or the first one if the file names none.
+    pub fn scene(&self) -> Option<&GltfScene> {
+        self.default_scene.or(if self.scenes.is_empty() { None } else { Some(0) })
+            .and_then(|index| self.scenes.get(index))
+    }
+
+    /// Material of a primitive, falling back to the default material.
+    pub fn material(&self, index: Option<usize>) -> GltfMaterial {
+        index.and_then(|index| self.materials.get(index))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Model matrix of every node, from the node transforms up to its root.
+    pub fn world_matrices(&self) -> Vec<na::Matrix4<f32>> {
+        let mut world = vec![na::Matrix4::identity(); self.nodes.len()];
+        let mut stack = (0..self.nodes.len())
+            .filter(|&i| self.nodes[i].parent.is_none())
+            .map(|i| (i, na::Matrix4::identity()))
+            .collect::<Vec<_>>();
+
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            world[index] = parent * node.transform.to_matrix();
+            for &child in node.children.iter() {
+                stack.push((child, world[index]));
+            }
+        }
+
+        world
+    }
+
+    /// Creates the GL texture for `textures[index]` with its sampling
+    /// parameters. Pass `srgb` for base color and emissive textures.
+    pub fn upload_texture(&self, gl: &gl::Gl, index: usize, srgb: bool) -> Texture {
+        let texture = &self.textures[index];
+
+        TextureBuilder::new()
+            .min_filter(texture.min_filter)
+            .mag_filter(texture.mag_filter)
+            .wrap_s(texture.wrap_s)
+            .wrap_t(texture.wrap_t)
+            .mipmaps(texture.min_filter.uses_mipmaps())
+            .srgb(srgb)
+            .flip_vertically(false)
+            .from_image_data(gl, &self.images[texture.image].data)
+    }
+}
+
+fn load(res: &Resources, name: &str) -> Result<Vec<u8>, Error> {
+    res.load_bytes(name)
+        .map_err(|e| Error::ResourceLoad {
+            name: name.into(),
+            inner: e
+        })
+}
+
+/// Contents of a `data:` URI or of the resource a relative URI points to.
+fn load_uri(res: &Resources, name: &str, uri: &str) -> Result<Vec<u8>, Error> {
+    if uri.starts_with("data:") {
+        let encoded = uri.splitn(2, ',').nth(1).unwrap_or("");
+        return base64::decode(encoded)
+            .map_err(|e| Error::DataUri {
+                name: name.into(),
+                inner: e
+            });
+    }
+
+    load(res, &resources::relative_name(name, &percent_decode(uri)))
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' {
+            uri.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn load_buffers(
+    res: &Resources,
+    name: &str,
+    document: &gltf::Document,
+    mut blob: Option<Vec<u8>>
+) -> Result<Vec<Vec<u8>>, Error> {
+    let mut buffers = Vec::new();
+
+    for buffer in document.buffers() {
+        let data = match buffer.source() {
+            gltf::buffer::Source::Bin => blob.take()
+                .ok_or_else(|| Error::MissingBlob { name: name.into() })?,
+            gltf::buffer::Source::Uri(uri) => load_uri(res, name, uri)?,
+        };
+        buffers.push(data);
+    }
+
+    Ok(buffers)
+}
+
+/// Bytes of `view`, or an error if its buffer is shorter than it claims.
+fn view_data<'a>(name: &str, view: &gltf::buffer::View, buffers: &'a [Vec<u8>]) -> Result<&'a [u8], Error> {
+    let buffer = buffers.get(view.buffer().index()).map(|buffer| &buffer[..]).unwrap_or(&[]);
+    let end = view.offset().saturating_add(view.length());
+
+    buffer.get(view.offset()..end)
+        .ok_or_else(|| Error::BufferViewOutOfRange {
+            name: name.into(),
+            view: view.index(),
+            end,
+            buffer_length: buffer.len()
+        })
+}
+
+/// Checks that every buffer view lies inside its buffer and every accessor
+/// inside its view.
+fn check_ranges(name: &str, document: &gltf::Document, buffers: &[Vec<u8>]) -> Result<(), Error> {
+    for view in document.views() {
+        view_data(name, &view, buffers)?;
+    }
+
+    for accessor in document.accessors() {
+        let view = match accessor.view() {
+            Some(view) => view,
+            None => continue,
+        };
+        if accessor.count() == 0 {
+            continue;
+        }
+
+        let stride = view.stride().unwrap_or_else(|| accessor.size());
+        let end = stride.checked_mul(accessor.count() - 1)
+            .and_then(|last| last.checked_add(accessor.offset()))
+            .and_then(|last| last.checked_add(accessor.size()));
+
+        if end.map_or(true, |end| end > view.length()) {
+            return Err(Error::AccessorOutOfRange {
+                name: name.into(),
+                accessor: accessor.index()
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn load_image(res: &Resources, name: &str, image: &gltf::Image, buffers: &[Vec<u8>]) -> Result<GltfImage, Error> {
+    let encoded = match image.source() {
+        gltf::image::Source::View { view, .. } => view_data(name, &view, buffers)?.to_vec(),
+        gltf::image::Source::Uri { uri, .. } => load_uri(res, name, uri)?,
+    };
+
+    let decoded = image::load_from_memory(&encoded)
+        .map_err(|e| Error::ImageDecode {
+            name: name.into(),
+            image: image.index(),
+            inner: e
+        })?;
+
+    Ok(GltfImage {
+        name: image.name().map(Into::into),
+        data: ImageData::from_dynamic(decoded),
+    })
+}
+
+fn material_from(material: &gltf::Material) -> GltfMaterial {
+    let pbr = material.pbr_metallic_roughness();
+    let texture_ref = |info: gltf::texture::Info| TextureRef {
+        texture: info.texture().index(),
+        tex_coord: info.tex_coord(),
+    };
+    let base_color = pbr.base_color_factor();
+    let emissive = material.emissive_factor();
+
+    GltfMaterial {
+        name: material.name().map(Into::into),
+        base_color: Color::linear(base_color[0], base_color[1], base_color[2], base_color[3]),
+        base_color_texture: pbr.base_color_texture().map(&texture_ref),
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+        metallic_roughness_texture: pbr.metallic_roughness_texture().map(&texture_ref),
+        normal_texture: material.normal_texture().map(|normal| TextureRef {
+            texture: normal.texture().index(),
+            tex_coord: normal.tex_coord(),
+        }),
+        normal_scale: material.normal_texture().map(|normal| normal.scale()).unwrap_or(1.0),
+        occlusion_texture: material.occlusion_texture().map(|occlusion| TextureRef {
+            texture: occlusion.texture().index(),
+            tex_coord: occlusion.tex_coord(),
+        }),
+        occlusion_strength: material.occlusion_texture().map(|occlusion| occlusion.strength()).unwrap_or(1.0),
+        emissive: Color::linear(emissive[0], emissive[1], emissive[2], 1.0),
+        emissive_texture: material.emissive_texture().map(&texture_ref),
+        alpha_mode: match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask { cutoff: material.alpha_cutoff() },
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        },
+        double_sided: material.double_sided(),
+    }
+}
+
+/// Primitives without normals are rendered flat: every triangle gets its
+/// own vertices with the face normal.
+fn flat_normals(primitive: &mut GltfPrimitive) {
+    let skinned = primitive.is_skinned();
+    let mut data = MeshData::default();
+    let mut joints = Vec::new();
+    let mut weights = Vec::new();
+
+    for triangle in primitive.data.indices.chunks(3) {
+        if triangle.len() < 3 {
+            break;
+        }
+
+        let corners = [
+            primitive.data.vertices[triangle[0] as usize],
+            primitive.data.vertices[triangle[1] as usize],
+            primitive.data.vertices[triangle[2] as usize],
+        ];
+        let p = corners.iter()
+            .map(|v| na::Vector3::new(v.pos.d0, v.pos.d1, v.pos.d2))
+            .collect::<Vec<_>>();
+        let n = (p[1] - p[0]).cross(&(p[2] - p[0]));
+        let n = if n.norm_squared() > 0.0 { n.normalize() } else { na::Vector3::z() };
+
+        for (corner, &index) in corners.iter().zip(triangle.iter()) {
+            let mut vertex = *corner;
+            vertex.normal = (n.x, n.y, n.z).into();
+            data.vertices.push(vertex);
+            let new_index = data.indices.len() as u32;
+            data.indices.push(new_index);
+
+            if skinned {
+                joints.push(primitive.joints[index as usize]);
+                weights.push(primitive.weights[index as usize]);
+            }
+        }
+    }
+
+    primitive.data = data;
+    primitive.joints = joints;
+    primitive.weights = weights;
+}
+
+fn matrix_from_columns(columns: &[[f32; 4]; 4]) -> na::Matrix4<f32> {
+    na::Matrix4::from_iterator(columns.iter().flat_map(|column| column.iter().cloned()))
+}
+
+fn primitive_mode(mode: gltf::mesh::Mode) -> Primitive {
+    match mode {
+        gltf::mesh::Mode::Points => Primitive::Points,
+        gltf::mesh::Mode::Lines => Primitive::Lines,
+        gltf::mesh::Mode::LineLoop => Primitive::LineLoop,
+        gltf::mesh::Mode::LineStrip => Primitive::LineStrip,
+        gltf::mesh::Mode::Triangles => Primitive::Triangles,
+        gltf::mesh::Mode::TriangleStrip => Primitive::TriangleStrip,
+        gltf::mesh::Mode::TriangleFan => Primitive::TriangleFan,
+    }
+}
+
+fn min_filter(filter: gltf::texture::MinFilter) -> MinFilter {
+    match filter {
+        gltf::texture::MinFilter::Nearest => MinFilter::Nearest,
+        gltf::texture::MinFilter::Linear => MinFilter::Linear,
+        gltf::texture::MinFilter::NearestMipmapNearest => MinFilter::NearestMipmapNearest,
+        gltf::texture::MinFilter::LinearMipmapNearest => MinFilter::LinearMipmapNearest,
+        gltf::texture::MinFilter::NearestMipmapLinear => MinFilter::NearestMipmapLinear,
+        gltf::texture::MinFilter::LinearMipmapLinear => MinFilter::LinearMipmapLinear,
+    }
+}
+
+fn mag_filter(filter: gltf::texture::MagFilter) -> MagFilter {
+    match filter {
+        gltf::texture::MagFilter::Nearest => MagFilter::Nearest,
+        gltf::texture::MagFilter::Linear => MagFilter::Linear,
+    }
+}
+
+fn wrap(mode: gltf::texture::WrappingMode) -> Wrap {
+    match mode {
+        gltf::texture::WrappingMode::ClampToEdge => Wrap::ClampToEdge,
+        gltf::texture::WrappingMode::MirroredRepeat => Wrap::MirroredRepeat,
+        gltf::texture::WrappingMode::Repeat => Wrap::Repeat,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GltfChannel, Interpolation, Property};
+
+    #[test]
+    fn rotation_between_antipodal_keys_stays_put() {
+        // a quarter turn about +Z, stored as q and then -q
+        let (s, c) = (std::f32::consts::FRAC_PI_4.sin(), std::f32::consts::FRAC_PI_4.cos());
+        let channel = GltfChannel {
+            node: 0,
+            property: Property::Rotation,
+            interpolation: Interpolation::Linear,
+            times: vec![0.0, 1.0],
+            values: vec![0.0, 0.0, s, c, 0.0, 0.0, -s, -c],
+        };
+
+        let q = channel.sample(0.5);
+        let dot = q[2] * s + q[3] * c;
+        assert!((dot.abs() - 1.0).abs() < 1e-5, "{:?} is not the key rotation", q);
+        assert!(q.iter().all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn rotation_takes_the_shorter_way_round() {
+        // identity to a quarter turn about +Z stored as -q, which is 135°
+        // away in quaternion space but only 90° as a rotation
+        let (s, c) = (std::f32::consts::FRAC_PI_4.sin(), std::f32::consts::FRAC_PI_4.cos());
+        let channel = GltfChannel {
+            node: 0,
+            property: Property::Rotation,
+            interpolation: Interpolation::Linear,
+            times: vec![0.0, 1.0],
+            values: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, -s, -c],
+        };
+
+        let q = channel.sample(0.5);
+        let (s, c) = ((std::f32::consts::PI / 8.0).sin(), (std::f32::consts::PI / 8.0).cos());
+        let dot = q[2] * s + q[3] * c;
+        assert!((dot.abs() - 1.0).abs() < 1e-5, "{:?} is not an eighth turn", q);
+    }
+}
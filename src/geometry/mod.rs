@@ -1,8 +1,16 @@
+mod shape;
 mod triangle;
 mod square;
 mod vertex;
+mod obj;
+mod gltf_import;
+pub mod primitives;
 
-pub use self::{
-    triangle::Triangle,
-    square::Square,
-};
\ No newline at end of file
+pub use self::shape::Shape;
+pub use self::vertex::{MeshVertex, SkinnedMeshVertex};
+pub use self::obj::{Error as ObjError, ObjMaterial, ObjMesh, ObjModel, ObjObject, ObjPart};
+pub use self::gltf_import::{
+    Error as GltfError, GltfModel, GltfScene, GltfNode, GltfMesh, GltfPrimitive, GltfMaterial,
+    GltfTexture, GltfImage, GltfCamera, GltfProjection, GltfSkin, GltfAnimation, GltfChannel,
+    TextureRef, AlphaMode, Property, Interpolation,
+};
@@ -0,0 +1,730 @@
+//! Wavefront OBJ meshes with MTL materials.
+//!
+//! Supported: `v`, `vt`, `vn`, polygon faces in all four index forms
+//! (including negative indices), `o`, `g`, `s`, `usemtl` and `mtllib`.
+//! Other statements such as lines, points and free-form surfaces are ignored.
+
+use gl;
+use nalgebra as na;
+use std::collections::HashMap;
+
+use geometry::primitives::MeshData;
+use geometry::vertex::MeshVertex;
+use render_gl::{Color, Mesh};
+use resources::{self, Resources};
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Unable to load resource {}", name)]
+    ResourceLoad {
+        name: String,
+        #[cause] inner: resources::Error
+    },
+
+    #[fail(display = "{}:{}: {}", name, line, message)]
+    Parse {
+        name: String,
+        line: usize,
+        message: String
+    },
+}
+
+/// MTL material. Colors are given in sRGB in the file and converted to
+/// linear; texture maps are resource names relative to the `Resources` root.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub ambient: Color,
+    pub diffuse: Color,
+    pub specular: Color,
+    pub emissive: Color,
+    pub shininess: f32,
+    pub opacity: f32,
+    pub illumination: u32,
+    pub diffuse_map: Option<String>,
+    pub specular_map: Option<String>,
+    pub normal_map: Option<String>,
+    pub alpha_map: Option<String>,
+}
+
+impl ObjMaterial {
+    fn new(name: &str) -> ObjMaterial {
+        ObjMaterial {
+            name: name.into(),
+            ambient: Color::linear(0.0, 0.0, 0.0, 1.0),
+            diffuse: Color::linear(0.8, 0.8, 0.8, 1.0),
+            specular: Color::linear(0.0, 0.0, 0.0, 1.0),
+            emissive: Color::linear(0.0, 0.0, 0.0, 1.0),
+            shininess: 0.0,
+            opacity: 1.0,
+            illumination: 2,
+            diffuse_map: None,
+            specular_map: None,
+            normal_map: None,
+            alpha_map: None,
+        }
+    }
+}
+
+/// Faces of one object or group that share a material.
+#[derive(Clone, Debug)]
+pub struct ObjPart {
+    /// Index into `ObjModel::materials`.
+    pub material: Option<usize>,
+    pub data: MeshData,
+}
+
+#[derive(Clone, Debug)]
+pub struct ObjObject {
+    pub name: String,
+    pub parts: Vec<ObjPart>,
+}
+
+/// One part on the GPU, ready to draw with its material.
+pub struct ObjMesh {
+    pub object: String,
+    pub material: Option<usize>,
+    pub mesh: Mesh<MeshVertex>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ObjModel {
+    pub objects: Vec<ObjObject>,
+    pub materials: Vec<ObjMaterial>,
+}
+
+impl ObjModel {
+    pub fn from_res(res: &Resources, name: &str) -> Result<ObjModel, Error> {
+        let source = load_text(res, name)?;
+        ObjModel::parse(res, name, &source)
+    }
+
+    /// Parses OBJ text; `name` is used in errors and to find `mtllib` files.
+    fn parse(res: &Resources, name: &str, source: &str) -> Result<ObjModel, Error> {
+        let mut parser = ObjParser::new(res, name);
+
+        for (i, line) in source.lines().enumerate() {
+            parser.line(i + 1, line)?;
+        }
+
+        Ok(parser.finish())
+    }
+
+    pub fn upload(&self, gl: &gl::Gl) -> Vec<ObjMesh> {
+        self.objects.iter()
+            .flat_map(|object| object.parts.iter().map(move |part| (object, part)))
+            .map(|(object, part)| ObjMesh {
+                object: object.name.clone(),
+                material: part.material,
+                mesh: part.data.upload(gl),
+            })
+            .collect()
+    }
+
+    pub fn material(&self, name: &str) -> Option<&ObjMaterial> {
+        self.materials.iter().find(|material| material.name == name)
+    }
+}
+
+fn load_text(res: &Resources, name: &str) -> Result<String, Error> {
+    let bytes = res.load_bytes(name)
+        .map_err(|e| Error::ResourceLoad {
+            name: name.into(),
+            inner: e
+        })?;
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Which normal a face corner uses: one from the file, or one computed
+/// from the faces around it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum NormalSource {
+    Given(usize),
+    /// Faces outside any smoothing group get their own flat normal.
+    Flat(usize),
+    /// Averaged over the faces of the group that share the position.
+    Smooth(u32, usize),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: NormalSource,
+}
+
+struct PartBuilder {
+    material: Option<usize>,
+    corners: Vec<Corner>,
+}
+
+struct ObjectBuilder {
+    name: String,
+    parts: Vec<PartBuilder>,
+}
+
+struct ObjParser<'a> {
+    res: &'a Resources,
+    name: &'a str,
+    positions: Vec<na::Vector3<f32>>,
+    uvs: Vec<(f32, f32)>,
+    normals: Vec<na::Vector3<f32>>,
+    materials: Vec<ObjMaterial>,
+    objects: Vec<ObjectBuilder>,
+    material: Option<usize>,
+    smoothing_group: u32,
+    face_count: usize,
+    /// Unnormalized sums of the face normals per computed normal.
+    generated_normals: HashMap<NormalSource, na::Vector3<f32>>,
+}
+
+impl<'a> ObjParser<'a> {
+    fn new(res: &'a Resources, name: &'a str) -> ObjParser<'a> {
+        ObjParser {
+            res,
+            name,
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            materials: Vec::new(),
+            objects: Vec::new(),
+            material: None,
+            smoothing_group: 0,
+            face_count: 0,
+            generated_normals: HashMap::new(),
+        }
+    }
+
+    fn error(&self, line: usize, message: String) -> Error {
+        Error::Parse {
+            name: self.name.into(),
+            line,
+            message
+        }
+    }
+
+    fn line(&mut self, number: usize, line: &str) -> Result<(), Error> {
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => return Ok(()),
+        };
+        let args = tokens.collect::<Vec<_>>();
+
+        match keyword {
+            "v" => {
+                let v = parse_floats(&args, 3, 4).map_err(|m| self.error(number, m))?;
+                self.positions.push(na::Vector3::new(v[0], v[1], v[2]));
+            },
+            "vt" => {
+                let v = parse_floats(&args, 1, 3).map_err(|m| self.error(number, m))?;
+                self.uvs.push((v[0], v.get(1).cloned().unwrap_or(0.0)));
+            },
+            "vn" => {
+                let v = parse_floats(&args, 3, 3).map_err(|m| self.error(number, m))?;
+                self.normals.push(na::Vector3::new(v[0], v[1], v[2]));
+            },
+            "f" => self.face(number, &args)?,
+            "o" | "g" => {
+                let name = args.join(" ");
+                self.start_object(if name.is_empty() { "default".into() } else { name });
+            },
+            "s" => {
+                let group = match args.first() {
+                    Some(&"off") | None => 0,
+                    Some(value) => value.parse()
+                        .map_err(|_| self.error(number, format!("invalid smoothing group '{}'", value)))?,
+                };
+                self.smoothing_group = group;
+            },
+            "usemtl" => {
+                let name = args.join(" ");
+                let index = self.materials.iter().position(|material| material.name == name)
+                    .ok_or_else(|| self.error(number, format!("unknown material '{}'", name)))?;
+                self.material = Some(index);
+            },
+            "mtllib" => {
+                for file in args.iter() {
+                    let mtl_name = resources::relative_name(self.name, file);
+                    let materials = load_materials(self.res, &mtl_name)?;
+                    self.materials.extend(materials);
+                }
+            },
+            _ => {},
+        }
+
+        Ok(())
+    }
+
+    fn start_object(&mut self, name: String) {
+        // `o` followed by `g` would otherwise leave an empty object behind
+        let reuse = self.objects.last()
+            .map(|object| object.parts.iter().all(|part| part.corners.is_empty()))
+            .unwrap_or(false);
+
+        if reuse {
+            self.objects.last_mut().unwrap().name = name;
+        } else {
+            self.objects.push(ObjectBuilder { name, parts: Vec::new() });
+        }
+    }
+
+    /// Part of the current object that collects faces with the current material.
+    fn current_part(&mut self) -> &mut PartBuilder {
+        if self.objects.is_empty() {
+            self.start_object("default".into());
+        }
+
+        let material = self.material;
+        let object = self.objects.last_mut().unwrap();
+        let existing = object.parts.iter().position(|part| part.material == material);
+        let index = match existing {
+            Some(index) => index,
+            None => {
+                object.parts.push(PartBuilder { material, corners: Vec::new() });
+                object.parts.len() - 1
+            },
+        };
+
+        &mut object.parts[index]
+    }
+
+    fn face(&mut self, number: usize, args: &[&str]) -> Result<(), Error> {
+        if args.len() < 3 {
+            return Err(self.error(number, format!("face needs at least 3 vertices, got {}", args.len())));
+        }
+
+        let mut refs = Vec::with_capacity(args.len());
+        for arg in args {
+            refs.push(self.face_vertex(number, arg)?);
+        }
+
+        let points = refs.iter().map(|r| self.positions[r.0]).collect::<Vec<_>>();
+        let triangles = triangulate(&points);
+
+        let face_id = self.face_count;
+        self.face_count += 1;
+        let smoothing_group = self.smoothing_group;
+
+        for triangle in triangles {
+            let (a, b, c) = (points[triangle[0]], points[triangle[1]], points[triangle[2]]);
+            // area weighted, so small slivers barely affect smooth normals
+            let face_normal = (b - a).cross(&(c - a));
+
+            for &i in triangle.iter() {
+                let (position, uv, normal) = refs[i];
+                let normal = match normal {
+                    Some(normal) => NormalSource::Given(normal),
+                    None => {
+                        let source = if smoothing_group == 0 {
+                            NormalSource::Flat(face_id)
+                        } else {
+                            NormalSource::Smooth(smoothing_group, position)
+                        };
+                        *self.generated_normals.entry(source).or_insert_with(na::Vector3::zeros) += face_normal;
+                        source
+                    },
+                };
+
+                self.current_part().corners.push(Corner { position, uv, normal });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves `v`, `v/vt`, `v//vn` or `v/vt/vn` to zero-based indices.
+    fn face_vertex(&self, number: usize, arg: &str) -> Result<(usize, Option<usize>, Option<usize>), Error> {
+        let mut parts = arg.split('/');
+
+        let resolve = |value: Option<&str>, count: usize, what: &str| -> Result<Option<usize>, Error> {
+            let value = match value {
+                Some(value) if !value.is_empty() => value,
+                _ => return Ok(None),
+            };
+
+            let index: i64 = value.parse()
+                .map_err(|_| self.error(number, format!("invalid {} index '{}'", what, value)))?;
+            let resolved = if index > 0 {
+                index - 1
+            } else {
+                count as i64 + index
+            };
+
+            if index == 0 || resolved < 0 || resolved >= count as i64 {
+                return Err(self.error(number, format!("{} index {} out of range, {} defined so far", what, index, count)));
+            }
+
+            Ok(Some(resolved as usize))
+        };
+
+        let position = resolve(parts.next(), self.positions.len(), "position")?
+            .ok_or_else(|| self.error(number, format!("face vertex '{}' has no position", arg)))?;
+        let uv = resolve(parts.next(), self.uvs.len(), "texture coordinate")?;
+        let normal = resolve(parts.next(), self.normals.len(), "normal")?;
+
+        Ok((position, uv, normal))
+    }
+
+    fn finish(self) -> ObjModel {
+        let mut objects = Vec::with_capacity(self.objects.len());
+
+        for object in self.objects.iter() {
+            let parts = object.parts.iter()
+                .filter(|part| !part.corners.is_empty())
+                .map(|part| ObjPart {
+                    material: part.material,
+                    data: self.build(part),
+                })
+                .collect::<Vec<_>>();
+
+            if !parts.is_empty() {
+                objects.push(ObjObject {
+                    name: object.name.clone(),
+                    parts,
+                });
+            }
+        }
+
+        ObjModel {
+            objects,
+            materials: self.materials,
+        }
+    }
+
+    /// Turns corners into vertices, sharing one vertex between all corners
+    /// with the same position, UV and normal.
+    fn build(&self, part: &PartBuilder) -> MeshData {
+        let mut data = MeshData::default();
+        let mut unique = HashMap::new();
+
+        for corner in part.corners.iter() {
+            let index = match unique.get(corner) {
+                Some(&index) => index,
+                None => {
+                    let p = self.positions[corner.position];
+                    let n = match corner.normal {
+                        NormalSource::Given(i) => self.normals[i],
+                        source => self.generated_normals[&source],
+                    };
+                    let n = if n.norm_squared() > 0.0 { n.normalize() } else { na::Vector3::y() };
+                    let uv = corner.uv.map(|i| self.uvs[i]).unwrap_or((0.0, 0.0));
+
+                    data.vertices.push(MeshVertex {
+                        pos: (p.x, p.y, p.z).into(),
+                        normal: (n.x, n.y, n.z).into(),
+                        tangent: (1.0, 0.0, 0.0, 1.0).into(),
+                        uv: uv.into(),
+                    });
+                    let index = (data.vertices.len() - 1) as u32;
+                    unique.insert(*corner, index);
+                    index
+                },
+            };
+            data.indices.push(index);
+        }
+
+        data.compute_tangents();
+        data
+    }
+}
+
+fn parse_floats(args: &[&str], min: usize, max: usize) -> Result<Vec<f32>, String> {
+    if args.len() < min || args.len() > max {
+        return Err(format!("expected {} to {} numbers, got {}", min, max, args.len()));
+    }
+
+    args.iter()
+        .map(|arg| arg.parse::<f32>().map_err(|_| format!("invalid number '{}'", arg)))
+        .collect()
+}
+
+/// Splits a polygon into triangles by ear clipping in the plane it mostly
+/// lies in, which also handles concave faces. Falls back to a fan if the
+/// polygon is degenerate or self-intersecting.
+fn triangulate(points: &[na::Vector3<f32>]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    if n == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    let fan = || (1..n - 1).map(|i| [0, i, i + 1]).collect::<Vec<_>>();
+
+    // Newell's method gives a robust normal even for concave polygons
+    let mut normal = na::Vector3::zeros();
+    for i in 0..n {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        normal += na::Vector3::new(
+            (a.y - b.y) * (a.z + b.z),
+            (a.z - b.z) * (a.x + b.x),
+            (a.x - b.x) * (a.y + b.y),
+        );
+    }
+    if normal.norm_squared() == 0.0 {
+        return fan();
+    }
+
+    // project onto the plane of the two axes the normal is least aligned with
+    let (ax, ay) = if normal.x.abs() >= normal.y.abs() && normal.x.abs() >= normal.z.abs() {
+        (1, 2)
+    } else if normal.y.abs() >= normal.z.abs() {
+        (2, 0)
+    } else {
+        (0, 1)
+    };
+    // swapping the axes mirrors the projection, so faces pointing down the
+    // negative axis are counter-clockwise too
+    let (ax, ay) = if normal[3 - ax - ay] < 0.0 { (ay, ax) } else { (ax, ay) };
+    let flat = points.iter()
+        .map(|p| na::Vector2::new(p[ax], p[ay]))
+        .collect::<Vec<_>>();
+
+    let cross = |o: na::Vector2<f32>, a: na::Vector2<f32>, b: na::Vector2<f32>| {
+        (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+    };
+
+    let mut remaining = (0..n).collect::<Vec<_>>();
+    let mut triangles = Vec::with_capacity(n - 2);
+
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let ear = (0..m).find(|&i| {
+            let (prev, cur, next) = (remaining[(i + m - 1) % m], remaining[i], remaining[(i + 1) % m]);
+            let (a, b, c) = (flat[prev], flat[cur], flat[next]);
+
+            // convex corner in counter-clockwise order, with no other vertex inside
+            cross(a, b, c) > 0.0 && remaining.iter()
+                .filter(|&&j| j != prev && j != cur && j != next)
+                .all(|&j| {
+                    let p = flat[j];
+                    cross(a, b, p) < 0.0 || cross(b, c, p) < 0.0 || cross(c, a, p) < 0.0
+                })
+        });
+
+        let i = match ear {
+            Some(i) => i,
+            None => return fan(),
+        };
+
+        triangles.push([remaining[(i + m - 1) % m], remaining[i], remaining[(i + 1) % m]]);
+        remaining.remove(i);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+    triangles
+}
+
+fn load_materials(res: &Resources, name: &str) -> Result<Vec<ObjMaterial>, Error> {
+    let source = load_text(res, name)?;
+    parse_materials(name, &source)
+}
+
+fn parse_materials(name: &str, source: &str) -> Result<Vec<ObjMaterial>, Error> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    let error = |line: usize, message: String| Error::Parse {
+        name: name.into(),
+        line,
+        message
+    };
+
+    for (i, line) in source.lines().enumerate() {
+        let number = i + 1;
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args = tokens.collect::<Vec<_>>();
+
+        if keyword == "newmtl" {
+            materials.push(ObjMaterial::new(&args.join(" ")));
+            continue;
+        }
+
+        let material = materials.last_mut()
+            .ok_or_else(|| error(number, format!("'{}' before any newmtl", keyword)))?;
+
+        let color = |args: &[&str]| -> Result<Color, Error> {
+            let v = parse_floats(args, 3, 3).map_err(|m| error(number, m))?;
+            Ok(Color::srgb(v[0], v[1], v[2], 1.0))
+        };
+        let scalar = |args: &[&str]| -> Result<f32, Error> {
+            parse_floats(args, 1, 1).map(|v| v[0]).map_err(|m| error(number, m))
+        };
+        // map options such as `-bm 1.0` come before the file name
+        let map = |args: &[&str]| -> Result<String, Error> {
+            args.last()
+                .map(|file| resources::relative_name(name, file))
+                .ok_or_else(|| error(number, format!("'{}' without a file name", keyword)))
+        };
+
+        match keyword {
+            "Ka" => material.ambient = color(&args)?,
+            "Kd" => material.diffuse = color(&args)?,
+            "Ks" => material.specular = color(&args)?,
+            "Ke" => material.emissive = color(&args)?,
+            "Ns" => material.shininess = scalar(&args)?,
+            "d" => material.opacity = scalar(&args)?,
+            "Tr" => material.opacity = 1.0 - scalar(&args)?,
+            "illum" => material.illumination = scalar(&args)? as u32,
+            "map_Kd" => material.diffuse_map = Some(map(&args)?),
+            "map_Ks" => material.specular_map = Some(map(&args)?),
+            "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_map = Some(map(&args)?),
+            "map_d" => material.alpha_map = Some(map(&args)?),
+            _ => {},
+        }
+    }
+
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra as na;
+    use std::path::Path;
+
+    use super::{parse_materials, triangulate, ObjModel};
+    use render_gl::Color;
+    use resources::Resources;
+
+    fn parse(source: &str) -> Result<ObjModel, String> {
+        let res = Resources::from_path(Path::new("assets"));
+        ObjModel::parse(&res, "models/test.obj", source).map_err(|e| e.to_string())
+    }
+
+    /// Twice the signed area in the XY plane, positive for counter-clockwise.
+    fn area2(a: na::Vector3<f32>, b: na::Vector3<f32>, c: na::Vector3<f32>) -> f32 {
+        (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+    }
+
+    #[test]
+    fn triangulates_concave_quad() {
+        // counter-clockwise dart with its reflex corner at index 1, where a
+        // fan from index 0 would put a triangle outside the polygon
+        let points = [
+            na::Vector3::new(4.0, 0.0, 0.0),
+            na::Vector3::new(1.0, 1.0, 0.0),
+            na::Vector3::new(0.0, 4.0, 0.0),
+            na::Vector3::new(0.0, 0.0, 0.0),
+        ];
+
+        let triangles = triangulate(&points);
+        assert_eq!(triangles.len(), 2);
+
+        let mut total = 0.0;
+        for t in triangles.iter() {
+            let area = area2(points[t[0]], points[t[1]], points[t[2]]);
+            assert!(area > 0.0, "triangle {:?} is flipped or degenerate", t);
+            total += area;
+        }
+        assert_eq!(total, 8.0);
+    }
+
+    #[test]
+    fn triangulates_concave_quad_facing_away() {
+        // the same dart in clockwise order, so its normal points down -Z
+        let points = [
+            na::Vector3::new(4.0, 0.0, 0.0),
+            na::Vector3::new(0.0, 0.0, 0.0),
+            na::Vector3::new(0.0, 4.0, 0.0),
+            na::Vector3::new(1.0, 1.0, 0.0),
+        ];
+
+        let triangles = triangulate(&points);
+        assert_eq!(triangles.len(), 2);
+
+        let mut total = 0.0;
+        for t in triangles.iter() {
+            let area = area2(points[t[0]], points[t[1]], points[t[2]]);
+            assert!(area < 0.0, "triangle {:?} is flipped or degenerate", t);
+            total += area;
+        }
+        assert_eq!(total, -8.0);
+    }
+
+    #[test]
+    fn falls_back_to_fan_for_degenerate_polygons() {
+        let points = [
+            na::Vector3::new(0.0, 0.0, 0.0),
+            na::Vector3::new(1.0, 0.0, 0.0),
+            na::Vector3::new(2.0, 0.0, 0.0),
+            na::Vector3::new(3.0, 0.0, 0.0),
+        ];
+
+        assert_eq!(triangulate(&points), vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn parses_concave_face() {
+        let model = parse("v 4 0 0\nv 1 1 0\nv 0 4 0\nv 0 0 0\nf 1 2 3 4\n").unwrap();
+
+        assert_eq!(model.objects.len(), 1);
+        assert_eq!(model.objects[0].name, "default");
+
+        let data = &model.objects[0].parts[0].data;
+        assert_eq!(data.vertices.len(), 4);
+        assert_eq!(data.indices.len(), 6);
+        for v in data.vertices.iter() {
+            assert_eq!((v.normal.d0, v.normal.d1, v.normal.d2), (0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn resolves_negative_indices() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nf -3/-3 -2/-2 -1/-1\n";
+        let data = &parse(source).unwrap().objects[0].parts[0].data;
+
+        let uvs = data.indices.iter()
+            .map(|&i| (data.vertices[i as usize].uv.d0, data.vertices[i as usize].uv.d1))
+            .collect::<Vec<_>>();
+        assert_eq!(uvs, vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]);
+    }
+
+    #[test]
+    fn reports_bad_index_with_line() {
+        let error = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\n\nf 1 2 4\n").unwrap_err();
+        assert_eq!(error, "models/test.obj:5: position index 4 out of range, 3 defined so far");
+
+        let error = parse("v 0 0 0\nf 1 -2 1\n").unwrap_err();
+        assert!(error.starts_with("models/test.obj:2: "), "{}", error);
+    }
+
+    #[test]
+    fn reports_malformed_statements_with_line() {
+        let error = parse("v 0 0\n").unwrap_err();
+        assert_eq!(error, "models/test.obj:1: expected 3 to 4 numbers, got 2");
+
+        let error = parse("v 0 0 0\nv 1 0 0\nf 1 2\n").unwrap_err();
+        assert!(error.starts_with("models/test.obj:3: "), "{}", error);
+    }
+
+    #[test]
+    fn parses_materials() {
+        let source = "newmtl red\nKd 1 0 0\nd 0.5\nmap_Kd -bm 1.0 textures/red.png\n\nnewmtl plain\n";
+        let materials = parse_materials("models/test.mtl", source).unwrap();
+
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials[0].name, "red");
+        assert_eq!(materials[0].diffuse, Color::srgb(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(materials[0].opacity, 0.5);
+        assert_eq!(materials[0].diffuse_map, Some("models/textures/red.png".into()));
+        assert_eq!(materials[1].name, "plain");
+        assert_eq!(materials[1].diffuse_map, None);
+    }
+
+    #[test]
+    fn reports_material_errors_with_line() {
+        let error = parse_materials("models/test.mtl", "# comment\nKd 1 0 0\n").unwrap_err();
+        assert_eq!(error.to_string(), "models/test.mtl:2: 'Kd' before any newmtl");
+    }
+}
@@ -0,0 +1,361 @@
+//! Procedural meshes for prototyping. Every shape is centered on the
+//! origin and fits a unit cube unless its size is a parameter, faces wind
+//! counter-clockwise seen from outside, and tangents are derived from the
+//! UVs. Segment and ring counts below the minimum a shape needs are raised.
+
+use gl;
+use nalgebra as na;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use geometry::vertex::MeshVertex;
+use render_gl::{Mesh, Primitive};
+
+/// Generated vertices and triangle list indices, still on the CPU.
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub fn upload(&self, gl: &gl::Gl) -> Mesh<MeshVertex> {
+        Mesh::indexed(gl, &self.vertices, &self.indices, Primitive::Triangles)
+    }
+
+    fn push(&mut self, pos: na::Vector3<f32>, normal: na::Vector3<f32>, uv: (f32, f32)) -> u32 {
+        self.vertices.push(MeshVertex {
+            pos: (pos.x, pos.y, pos.z).into(),
+            normal: (normal.x, normal.y, normal.z).into(),
+            tangent: (1.0, 0.0, 0.0, 1.0).into(),
+            uv: uv.into(),
+        });
+
+        (self.vertices.len() - 1) as u32
+    }
+
+    /// `(columns + 1) * (rows + 1)` vertices from `f(column, row)`, joined
+    /// into quads. Faces wind counter-clockwise around the direction of
+    /// `d(pos)/d(column) x d(pos)/d(row)`.
+    fn grid<F>(&mut self, columns: u32, rows: u32, f: F)
+        where F: Fn(u32, u32) -> (na::Vector3<f32>, na::Vector3<f32>, (f32, f32))
+    {
+        let base = self.vertices.len() as u32;
+        for row in 0..rows + 1 {
+            for column in 0..columns + 1 {
+                let (pos, normal, uv) = f(column, row);
+                self.push(pos, normal, uv);
+            }
+        }
+
+        let stride = columns + 1;
+        for row in 0..rows {
+            for column in 0..columns {
+                let a = base + row * stride + column;
+                let b = a + 1;
+                let c = a + stride + 1;
+                let d = a + stride;
+                self.indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+    }
+
+    /// Flat disc of `radius` at height `y`, facing up or down, with planar UVs.
+    fn disc(&mut self, radius: f32, y: f32, up: bool, segments: u32, rings: u32) {
+        // mirroring z flips the winding, so both sides face outwards
+        let z_sign = if up { 1.0 } else { -1.0 };
+        let normal = na::Vector3::new(0.0, if up { 1.0 } else { -1.0 }, 0.0);
+
+        self.grid(segments, rings, |column, ring| {
+            let angle = column as f32 / segments as f32 * 2.0 * PI;
+            let r = ring as f32 / rings as f32;
+            let (x, z) = (r * angle.cos(), z_sign * r * angle.sin());
+            (
+                na::Vector3::new(x * radius, y, z * radius),
+                normal,
+                (0.5 + x * 0.5, 0.5 - z * 0.5 * z_sign),
+            )
+        });
+    }
+
+    /// Accumulates per-triangle tangents from positions and UVs, then
+    /// orthogonalizes them against the normals.
+    pub(crate) fn compute_tangents(&mut self) {
+        let mut tangents = vec![na::Vector3::zeros(); self.vertices.len()];
+        let mut bitangents = vec![na::Vector3::zeros(); self.vertices.len()];
+
+        let pos = |v: &MeshVertex| na::Vector3::new(v.pos.d0, v.pos.d1, v.pos.d2);
+        let uv = |v: &MeshVertex| na::Vector2::new(v.uv.d0, v.uv.d1);
+
+        for triangle in self.indices.chunks(3) {
+            let (i0, i1, i2) = (triangle[0] as usize, triangle[1] as usize, triangle[2] as usize);
+            let (v0, v1, v2) = (&self.vertices[i0], &self.vertices[i1], &self.vertices[i2]);
+
+            let e1 = pos(v1) - pos(v0);
+            let e2 = pos(v2) - pos(v0);
+            let d1 = uv(v1) - uv(v0);
+            let d2 = uv(v2) - uv(v0);
+
+            let det = d1.x * d2.y - d2.x * d1.y;
+            if det.abs() < 1e-12 {
+                continue;
+            }
+            let r = 1.0 / det;
+            let t = (e1 * d2.y - e2 * d1.y) * r;
+            let b = (e2 * d1.x - e1 * d2.x) * r;
+
+            for &i in &[i0, i1, i2] {
+                tangents[i] += t;
+                bitangents[i] += b;
+            }
+        }
+
+        for (i, vertex) in self.vertices.iter_mut().enumerate() {
+            let n = na::Vector3::new(vertex.normal.d0, vertex.normal.d1, vertex.normal.d2);
+            let mut t = tangents[i] - n * n.dot(&tangents[i]);
+
+            if t.norm_squared() < 1e-12 {
+                // no usable UV gradient here (poles, disc centers): any
+                // direction perpendicular to the normal will do
+                let axis = if n.x.abs() < 0.9 { na::Vector3::x() } else { na::Vector3::y() };
+                t = axis - n * n.dot(&axis);
+            }
+            let t = t.normalize();
+
+            let w = if n.cross(&t).dot(&bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+            vertex.tangent = (t.x, t.y, t.z, w).into();
+        }
+    }
+
+    /// Turns a mesh built facing +Y to face +Z.
+    fn rotated_to_xy(mut self) -> MeshData {
+        for vertex in self.vertices.iter_mut() {
+            let (y, z) = (vertex.pos.d1, vertex.pos.d2);
+            vertex.pos = (vertex.pos.d0, -z, y).into();
+            let (ny, nz) = (vertex.normal.d1, vertex.normal.d2);
+            vertex.normal = (vertex.normal.d0, -nz, ny).into();
+            let (ty, tz) = (vertex.tangent.d1, vertex.tangent.d2);
+            vertex.tangent = (vertex.tangent.d0, -tz, ty, vertex.tangent.d3).into();
+        }
+        self
+    }
+
+    fn finish(mut self) -> MeshData {
+        self.compute_tangents();
+        self
+    }
+}
+
+/// 1x1 square in the XY plane, facing +Z.
+pub fn quad() -> MeshData {
+    plane_grid(1, 1).rotated_to_xy()
+}
+
+/// 1x1 grid in the XZ plane, facing +Y, with `columns x rows` cells.
+pub fn plane_grid(columns: u32, rows: u32) -> MeshData {
+    let (columns, rows) = (columns.max(1), rows.max(1));
+    let mut data = MeshData::default();
+
+    data.grid(columns, rows, |column, row| {
+        let (u, v) = (column as f32 / columns as f32, row as f32 / rows as f32);
+        (na::Vector3::new(u - 0.5, 0.0, 0.5 - v), na::Vector3::y(), (u, v))
+    });
+
+    data.finish()
+}
+
+/// Unit cube; every face is split into `subdivisions x subdivisions` quads
+/// and has its own vertices, normals and full 0..1 UVs.
+pub fn cube(subdivisions: u32) -> MeshData {
+    let n = subdivisions.max(1);
+    let mut data = MeshData::default();
+
+    // (normal, u axis, v axis) with u x v == normal
+    let faces = [
+        (na::Vector3::x(), -na::Vector3::z(), na::Vector3::y()),
+        (-na::Vector3::x(), na::Vector3::z(), na::Vector3::y()),
+        (na::Vector3::y(), na::Vector3::x(), -na::Vector3::z()),
+        (-na::Vector3::y(), na::Vector3::x(), na::Vector3::z()),
+        (na::Vector3::z(), na::Vector3::x(), na::Vector3::y()),
+        (-na::Vector3::z(), -na::Vector3::x(), na::Vector3::y()),
+    ];
+
+    for &(normal, u_axis, v_axis) in faces.iter() {
+        data.grid(n, n, |column, row| {
+            let (u, v) = (column as f32 / n as f32, row as f32 / n as f32);
+            let pos = normal * 0.5 + u_axis * (u - 0.5) + v_axis * (v - 0.5);
+            (pos, normal, (u, v))
+        });
+    }
+
+    data.finish()
+}
+
+/// Point on a sphere of radius 1; `polar` runs from the south pole (0) to
+/// the north pole (PI), `azimuth` from +X towards -Z.
+fn sphere_point(polar: f32, azimuth: f32) -> na::Vector3<f32> {
+    na::Vector3::new(polar.sin() * azimuth.cos(), -polar.cos(), -polar.sin() * azimuth.sin())
+}
+
+/// Sphere of diameter 1 with `segments` around and `rings` from pole to pole.
+pub fn uv_sphere(segments: u32, rings: u32) -> MeshData {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+    let mut data = MeshData::default();
+
+    data.grid(segments, rings, |column, ring| {
+        let (u, v) = (column as f32 / segments as f32, ring as f32 / rings as f32);
+        let normal = sphere_point(v * PI, u * 2.0 * PI);
+        (normal * 0.5, normal, (u, v))
+    });
+
+    data.finish()
+}
+
+/// Sphere of diameter 1 made by splitting every triangle of an icosahedron
+/// into four, `subdivisions` times. Triangles are evenly sized, but the
+/// spherical UVs have a seam at -X and pinch at the poles.
+pub fn icosphere(subdivisions: u32) -> MeshData {
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+    let mut points = vec![
+        na::Vector3::new(-1.0, t, 0.0), na::Vector3::new(1.0, t, 0.0),
+        na::Vector3::new(-1.0, -t, 0.0), na::Vector3::new(1.0, -t, 0.0),
+        na::Vector3::new(0.0, -1.0, t), na::Vector3::new(0.0, 1.0, t),
+        na::Vector3::new(0.0, -1.0, -t), na::Vector3::new(0.0, 1.0, -t),
+        na::Vector3::new(t, 0.0, -1.0), na::Vector3::new(t, 0.0, 1.0),
+        na::Vector3::new(-t, 0.0, -1.0), na::Vector3::new(-t, 0.0, 1.0),
+    ].into_iter().map(|p| p.normalize()).collect::<Vec<_>>();
+
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32, points: &mut Vec<na::Vector3<f32>>| {
+            let key = (a.min(b), a.max(b));
+            *midpoints.entry(key).or_insert_with(|| {
+                let p = ((points[a as usize] + points[b as usize]) * 0.5).normalize();
+                points.push(p);
+                (points.len() - 1) as u32
+            })
+        };
+
+        let mut next = Vec::with_capacity(triangles.len() * 4);
+        for triangle in triangles.iter() {
+            let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+            let ab = midpoint(a, b, &mut points);
+            let bc = midpoint(b, c, &mut points);
+            let ca = midpoint(c, a, &mut points);
+            next.push([a, ab, ca]);
+            next.push([b, bc, ab]);
+            next.push([c, ca, bc]);
+            next.push([ab, bc, ca]);
+        }
+        triangles = next;
+    }
+
+    let mut data = MeshData::default();
+    for p in points.iter() {
+        let u = 0.5 + (-p.z).atan2(p.x) / (2.0 * PI);
+        let v = 0.5 + p.y.max(-1.0).min(1.0).asin() / PI;
+        data.push(*p * 0.5, *p, (u, v));
+    }
+    for triangle in triangles.iter() {
+        data.indices.extend_from_slice(triangle);
+    }
+
+    data.finish()
+}
+
+/// Cylinder of diameter 1 and height 1 along Y, with capped ends.
+pub fn cylinder(segments: u32, height_segments: u32) -> MeshData {
+    let (segments, height_segments) = (segments.max(3), height_segments.max(1));
+    let mut data = MeshData::default();
+
+    data.grid(segments, height_segments, |column, row| {
+        let (u, v) = (column as f32 / segments as f32, row as f32 / height_segments as f32);
+        let angle = u * 2.0 * PI;
+        let normal = na::Vector3::new(angle.cos(), 0.0, -angle.sin());
+        (normal * 0.5 + na::Vector3::new(0.0, v - 0.5, 0.0), normal, (u, v))
+    });
+    data.disc(0.5, 0.5, true, segments, 1);
+    data.disc(0.5, -0.5, false, segments, 1);
+
+    data.finish()
+}
+
+/// Cone of base diameter 1 and height 1 along Y, tip up, with a capped base.
+pub fn cone(segments: u32, height_segments: u32) -> MeshData {
+    let (segments, height_segments) = (segments.max(3), height_segments.max(1));
+    let mut data = MeshData::default();
+
+    // the side slopes 0.5 inwards per unit of height
+    data.grid(segments, height_segments, |column, row| {
+        let (u, v) = (column as f32 / segments as f32, row as f32 / height_segments as f32);
+        let angle = u * 2.0 * PI;
+        let (c, s) = (angle.cos(), -angle.sin());
+        let radius = 0.5 * (1.0 - v);
+        (
+            na::Vector3::new(c * radius, v - 0.5, s * radius),
+            na::Vector3::new(c, 0.5, s).normalize(),
+            (u, v),
+        )
+    });
+    data.disc(0.5, -0.5, false, segments, 1);
+
+    data.finish()
+}
+
+/// Cylinder of diameter 1 and `height` with hemispheres on both ends, so
+/// the total height is `height + 1`. `rings` is per hemisphere.
+pub fn capsule(segments: u32, rings: u32, height: f32) -> MeshData {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    let rows = rings * 2 + 1;
+    let mut data = MeshData::default();
+
+    data.grid(segments, rows, |column, row| {
+        let u = column as f32 / segments as f32;
+
+        // rows 0..=rings walk the bottom hemisphere up to the equator, the
+        // rest walk the top one; the quads in between form the body
+        let (polar, y) = if row <= rings {
+            (row as f32 / rings as f32 * PI * 0.5, -height * 0.5)
+        } else {
+            ((1.0 + (row - rings - 1) as f32 / rings as f32) * PI * 0.5, height * 0.5)
+        };
+
+        let normal = sphere_point(polar, u * 2.0 * PI);
+        let pos = normal * 0.5 + na::Vector3::new(0.0, y, 0.0);
+        (pos, normal, (u, (pos.y + height * 0.5 + 0.5) / (height + 1.0)))
+    });
+
+    data.finish()
+}
+
+/// Ring of radius `radius` around Y made of a tube of radius `tube_radius`,
+/// with `segments` around the ring and `sides` around the tube.
+pub fn torus(radius: f32, tube_radius: f32, segments: u32, sides: u32) -> MeshData {
+    let (segments, sides) = (segments.max(3), sides.max(3));
+    let mut data = MeshData::default();
+
+    data.grid(segments, sides, |column, side| {
+        let (u, v) = (column as f32 / segments as f32, side as f32 / sides as f32);
+        let (ring_angle, tube_angle) = (u * 2.0 * PI, v * 2.0 * PI);
+
+        let outward = na::Vector3::new(ring_angle.cos(), 0.0, -ring_angle.sin());
+        let normal = outward * tube_angle.cos() + na::Vector3::y() * tube_angle.sin();
+        (outward * radius + normal * tube_radius, normal, (u, v))
+    });
+
+    data.finish()
+}
+
+/// Flat disc of diameter 1 in the XZ plane, facing +Y.
+pub fn disc(segments: u32, rings: u32) -> MeshData {
+    let mut data = MeshData::default();
+    data.disc(0.5, 0.0, true, segments.max(3), rings.max(1));
+    data.finish()
+}
@@ -0,0 +1,67 @@
+use gl;
+use failure;
+use nalgebra as na;
+use render_gl::{self, Mesh, Viewport};
+use resources::Resources;
+use geometry::vertex::Vertex;
+use scene::{Camera, Renderable, Transform};
+
+/// Colored mesh drawn with the `shaders/triangle` program. The shapes
+/// themselves are constructors in their own modules, e.g. `Shape::square`.
+pub struct Shape {
+    transform: Transform,
+    program: render_gl::Program,
+    mesh: Mesh<Vertex>,
+}
+
+impl Shape {
+    pub fn new(res: &Resources, gl: &gl::Gl, mesh: Mesh<Vertex>, scale: f32) -> Result<Shape, failure::Error> {
+        let program = render_gl::Program::from_res(gl, res, "shaders/triangle")?;
+
+        Ok(Shape {
+            transform: Transform::from_scale(scale),
+            program,
+            mesh,
+        })
+    }
+
+    pub fn mesh_mut(&mut self) -> &mut Mesh<Vertex> {
+        &mut self.mesh
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    pub fn update_pos(&mut self, vec: &na::Vector3<f32>) {
+        self.transform.translate(vec);
+    }
+
+    /// Draws without a camera, with the transform mapping straight to
+    /// clip space.
+    pub fn render(&self) {
+        self.program.bind();
+        self.program.set_uniform_mat4f("View\0", &na::Matrix4::identity());
+        self.program.set_uniform_mat4f("Projection\0", &na::Matrix4::identity());
+        self.draw_model(&na::Matrix4::identity());
+    }
+
+    fn draw_model(&self, world: &na::Matrix4<f32>) {
+        self.program.set_uniform_mat4f("ModelMatrix\0", &(world * self.transform.to_matrix()));
+        self.mesh.draw();
+    }
+}
+
+impl Renderable for Shape {
+    /// Draws with `world * transform`, so the shape keeps its own scale
+    /// and offset relative to the scene node it is attached to.
+    fn draw(&self, world: &na::Matrix4<f32>, camera: &Camera, viewport: &Viewport) {
+        self.program.bind();
+        camera.apply(&self.program, viewport);
+        self.draw_model(world);
+    }
+}
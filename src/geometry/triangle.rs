@@ -1,21 +1,12 @@
 use gl;
 use failure;
-use nalgebra as na;
-use render_gl::{self, buffer, data};
+use render_gl::{Mesh, Primitive};
 use resources::Resources;
+use geometry::shape::Shape;
 use geometry::vertex::Vertex;
 
-pub struct Triangle {
-    model_matrix: na::Matrix4<f32>,
-    program: render_gl::Program,
-    _vbo: buffer::VertexBuffer,
-    vao: buffer::VertexArray,
-}
-
-impl Triangle {
-    pub fn new(res: &Resources, gl: &gl::Gl) -> Result<Triangle, failure::Error> {
-        let program = render_gl::Program::from_res(gl, res, "shaders/triangle")?;
-
+impl Shape {
+    pub fn triangle(res: &Resources, gl: &gl::Gl) -> Result<Shape, failure::Error> {
         let vertices: [Vertex; 3] = [
             Vertex {
                 pos: (0.5, -0.5, 0.0).into(),
@@ -31,37 +22,7 @@ impl Triangle {
             }  // top
         ];
 
-        let vao = buffer::VertexArray::new(gl);
-        vao.bind();
-
-        let vbo = buffer::VertexBuffer::new(gl);
-        vbo.bind();
-
-        vbo.static_draw_data(&vertices);
-        Vertex::vertex_attrib_pointers(gl);
-        vao.unbind();
-        vbo.unbind();
-
-        Ok(Triangle {
-            model_matrix: na::Matrix4::new_scaling(0.33),
-            program,
-            _vbo: vbo,
-            vao,
-        })
+        let mesh = Mesh::new(gl, &vertices, Primitive::Triangles);
+        Shape::new(res, gl, mesh, 0.33)
     }
-
-    pub fn update_pos(&mut self, vec: &na::Vector3<f32>) {
-        self.model_matrix = self.model_matrix.append_translation(vec);
-    }
-
-    pub fn render(&self, gl: &gl::Gl) {
-        self.program.bind();
-        self.program.set_uniform_mat4f("ModelMatrix\0", &self.model_matrix);
-
-        self.vao.bind();
-
-        unsafe {
-            gl.DrawArrays(gl::TRIANGLES, 0, 3);
-        }
-    }
-}
\ No newline at end of file
+}
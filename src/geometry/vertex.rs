@@ -8,4 +8,40 @@ pub struct Vertex {
     pub pos: data::f32_f32_f32,
     #[location = "1"]
     pub clr: data::u2_u10_u10_u10_rev_float,
-}
\ No newline at end of file
+}
+
+/// Vertex of the generated primitives. `tangent.w` is the handedness of
+/// the bitangent, `cross(normal, tangent.xyz) * tangent.w`.
+#[derive(VertexAttribPointers)]
+#[derive(Copy, Clone, Debug)]
+#[repr(C, packed)]
+pub struct MeshVertex {
+    #[location = "0"]
+    pub pos: data::f32_f32_f32,
+    #[location = "1"]
+    pub normal: data::f32_f32_f32,
+    #[location = "2"]
+    pub tangent: data::f32_f32_f32_f32,
+    #[location = "3"]
+    pub uv: data::f32_f32,
+}
+
+/// `MeshVertex` with the four joints that move it, as indices into the
+/// skin's joint list, and their weights, which add up to one.
+#[derive(VertexAttribPointers)]
+#[derive(Copy, Clone, Debug)]
+#[repr(C, packed)]
+pub struct SkinnedMeshVertex {
+    #[location = "0"]
+    pub pos: data::f32_f32_f32,
+    #[location = "1"]
+    pub normal: data::f32_f32_f32,
+    #[location = "2"]
+    pub tangent: data::f32_f32_f32_f32,
+    #[location = "3"]
+    pub uv: data::f32_f32,
+    #[location = "4"]
+    pub joints: data::u16_u16_u16_u16,
+    #[location = "5"]
+    pub weights: data::f32_f32_f32_f32,
+}
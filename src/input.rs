@@ -1,11 +1,31 @@
 use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use sdl2::video::Window;
 
+/// Keyboard and mouse state built from SDL events.
+///
+/// Mouse positions and motion are in drawable pixels from the top-left
+/// corner, the space `Viewport` works in, so they stay correct on HiDPI
+/// displays. Motion and wheel accumulate until `begin_frame`.
 pub struct Input {
     pub up: i8,
     pub down: i8,
     pub left: i8,
     pub right: i8,
+    pub ascend: i8,
+    pub descend: i8,
+    pub fast: bool,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+    pub mouse_dx: f32,
+    pub mouse_dy: f32,
+    /// Wheel notches, positive away from the user.
+    pub wheel: f32,
+    pub mouse_left: bool,
+    pub mouse_middle: bool,
+    pub mouse_right: bool,
+    pixel_scale: f32,
 }
 
 impl Input {
@@ -16,9 +36,38 @@ impl Input {
             down: 0,
             left: 0,
             right: 0,
+            ascend: 0,
+            descend: 0,
+            fast: false,
+            mouse_x: 0.0,
+            mouse_y: 0.0,
+            mouse_dx: 0.0,
+            mouse_dy: 0.0,
+            wheel: 0.0,
+            mouse_left: false,
+            mouse_middle: false,
+            mouse_right: false,
+            pixel_scale: 1.0,
         }
     }
 
+    /// Drawable pixels per window coordinate; call at startup and on
+    /// `WindowEvent::SizeChanged`.
+    pub fn update_pixel_scale(&mut self, window: &Window) {
+        let (w, _) = window.size();
+        let (drawable_w, _) = window.drawable_size();
+        if w > 0 {
+            self.pixel_scale = drawable_w as f32 / w as f32;
+        }
+    }
+
+    /// Clears the per-frame mouse motion and wheel; call before polling events.
+    pub fn begin_frame(&mut self) {
+        self.mouse_dx = 0.0;
+        self.mouse_dy = 0.0;
+        self.wheel = 0.0;
+    }
+
     pub fn callback(&mut self, event: &sdl2::event::Event) {
         match event {
             Event::KeyDown {keycode: Some(key), ..} => {
@@ -35,6 +84,15 @@ impl Input {
                     Keycode::S | Keycode::Down => {
                         self.down = 1;
                     },
+                    Keycode::E | Keycode::Space => {
+                        self.ascend = 1;
+                    },
+                    Keycode::Q | Keycode::LCtrl => {
+                        self.descend = 1;
+                    },
+                    Keycode::LShift | Keycode::RShift => {
+                        self.fast = true;
+                    },
                     _ => {},
                 }
             },
@@ -52,11 +110,48 @@ impl Input {
                     Keycode::S | Keycode::Down => {
                         self.down = 0;
                     },
+                    Keycode::E | Keycode::Space => {
+                        self.ascend = 0;
+                    },
+                    Keycode::Q | Keycode::LCtrl => {
+                        self.descend = 0;
+                    },
+                    Keycode::LShift | Keycode::RShift => {
+                        self.fast = false;
+                    },
                     _ => {},
                 }
             },
+            Event::MouseMotion {x, y, xrel, yrel, ..} => {
+                self.mouse_x = *x as f32 * self.pixel_scale;
+                self.mouse_y = *y as f32 * self.pixel_scale;
+                self.mouse_dx += *xrel as f32 * self.pixel_scale;
+                self.mouse_dy += *yrel as f32 * self.pixel_scale;
+            },
+            Event::MouseButtonDown {mouse_btn, ..} => self.set_button(*mouse_btn, true),
+            Event::MouseButtonUp {mouse_btn, ..} => self.set_button(*mouse_btn, false),
+            Event::MouseWheel {y, ..} => {
+                self.wheel += *y as f32;
+            },
+            Event::Window {win_event: WindowEvent::FocusLost, ..} => {
+                // key and button releases are not delivered while unfocused
+                *self = Input {
+                    mouse_x: self.mouse_x,
+                    mouse_y: self.mouse_y,
+                    pixel_scale: self.pixel_scale,
+                    ..Input::new()
+                };
+            },
             _ => {},
         }
     }
-}
 
+    fn set_button(&mut self, button: MouseButton, down: bool) {
+        match button {
+            MouseButton::Left => self.mouse_left = down,
+            MouseButton::Middle => self.mouse_middle = down,
+            MouseButton::Right => self.mouse_right = down,
+            _ => {},
+        }
+    }
+}
@@ -2,18 +2,29 @@ extern crate gl;
 extern crate sdl2;
 extern crate image;
 extern crate nalgebra;
+extern crate png;
 extern crate vec_2_10_10_10;
+extern crate gltf;
+extern crate base64;
 #[macro_use] extern crate failure;
 #[macro_use] extern crate render_gl_derive;
 
 pub mod render_gl;
 pub mod resources;
 mod geometry;
+mod scene;
 mod debug;
 mod input;
+#[cfg(test)] mod tests;
 
 use nalgebra as na;
 
+/// Samples per pixel of the window's default framebuffer; 0 disables MSAA.
+const MSAA_SAMPLES: u8 = 4;
+
+/// Let GL encode linear shader output to sRGB when writing to the window.
+const SRGB_FRAMEBUFFER: bool = true;
+
 fn main() {
     if let Err(e) = run() {
         println!("{}", debug::failure_to_string(e));
@@ -30,10 +41,20 @@ fn run() -> Result<(), failure::Error> {
     gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
     gl_attr.set_context_version(4, 5);
 
+    if MSAA_SAMPLES > 0 {
+        gl_attr.set_multisample_buffers(1);
+        gl_attr.set_multisample_samples(MSAA_SAMPLES);
+    }
+
+    if SRGB_FRAMEBUFFER {
+        gl_attr.set_framebuffer_srgb_compatible(true);
+    }
+
     let window = video_subsystem
         .window("Game", 900, 700)
         .opengl()
         .resizable()
+        .allow_highdpi()
         .build()
         .unwrap();
 
@@ -42,48 +63,149 @@ fn run() -> Result<(), failure::Error> {
     let gl = gl::Gl::load_with(
         |s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void
     );
-    let mut viewport = render_gl::Viewport::for_window(900, 700);
-    let color_buffer = render_gl::ColorBuffer::from_color(na::Vector3::new(0.3, 0.3, 0.5));
+    let mut viewport = render_gl::Viewport::for_sdl_window(&window);
+    let color_buffer = render_gl::ColorBuffer::new(render_gl::Color::srgb(0.3, 0.3, 0.5, 1.0));
 
     viewport.enable(&gl);
     color_buffer.enable(&gl);
 
+    if MSAA_SAMPLES > 0 {
+        unsafe {
+            gl.Enable(gl::MULTISAMPLE);
+        }
+    }
+
+    if SRGB_FRAMEBUFFER {
+        unsafe {
+            gl.Enable(gl::FRAMEBUFFER_SRGB);
+        }
+    }
+
     use std::path::Path;
     use resources::Resources;
 
     let res = Resources::from_relative_exe_path(Path::new("assets"))?;
     let mut input = input::Input::new();
-    let mut square = geometry::Square::new(&res, &gl)?;
+    input.update_pixel_scale(&window);
     let mut translation = na::Vector3::new(0.0, 0.0, 0.0);
 
+    let mut camera = scene::Camera::perspective(std::f32::consts::FRAC_PI_3, 0.1, 100.0);
+    camera.look_at(&na::Point3::new(0.0, 0.0, 1.2), &na::Point3::origin(), &na::Vector3::y());
+    let mut orbit = scene::OrbitController::from_camera(&camera, &na::Point3::origin());
+    let mut fly: Option<scene::FlyController> = None;
+    // 2D view and the 3D camera to return to
+    let mut pan_zoom: Option<(scene::PanZoomController, scene::Camera)> = None;
+
+    let mut scene = scene::SceneGraph::new();
+    let square = scene.add("square");
+    scene.set_renderable(square, Box::new(geometry::Shape::square(&res, &gl)?));
+    let triangle = scene.add_child(square, "triangle");
+    scene.set_renderable(triangle, Box::new(geometry::Shape::triangle(&res, &gl)?));
+    scene.set_local(triangle, scene::Transform {
+        translation: na::Vector3::new(0.4, 0.4, 0.0),
+        ..scene::Transform::from_scale(0.5)
+    });
+
+    let mut post = render_gl::PostProcess::new(&gl, &viewport, MSAA_SAMPLES as u32)?;
+    for name in &["shaders/post/tonemap", "shaders/post/fxaa", "shaders/post/vignette"] {
+        post.add_pass(render_gl::PostPass::from_res(&gl, &res, name)?);
+    }
+
     let mut event_pump = sdl.event_pump().unwrap();
+    let mut last_frame = std::time::Instant::now();
     'main: loop {
+        input.begin_frame();
         for event in event_pump.poll_iter() {
             use sdl2::event::{Event, WindowEvent};
             use sdl2::keyboard::Keycode;
 
             match event {
                 Event::Quit {..} => break 'main,
-                Event::Window {win_event: WindowEvent::Resized(w, h), ..} => {
-                    viewport.update_size(w, h);
+                Event::Window {win_event: WindowEvent::SizeChanged(..), ..} => {
+                    viewport.update_size_from(&window);
                     viewport.enable(&gl);
+                    post.resize(&viewport)?;
+                    input.update_pixel_scale(&window);
                 },
                 Event::KeyDown {keycode: Some(key), ..} |
                 Event::KeyUp {keycode: Some(key), ..} => {
                     match key {
                         Keycode::Escape => break 'main,
+                        Keycode::Num1 | Keycode::Num2 | Keycode::Num3 => {
+                            if let Event::KeyDown {repeat: false, ..} = event {
+                                let index = match key {
+                                    Keycode::Num1 => 0,
+                                    Keycode::Num2 => 1,
+                                    _ => 2,
+                                };
+                                let name = post.passes()[index].name().to_string();
+                                let enabled = post.passes()[index].is_enabled();
+                                post.set_enabled(&name, !enabled);
+                            }
+                        },
+                        Keycode::P => {
+                            if let Event::KeyDown {repeat: false, ..} = event {
+                                pan_zoom = match pan_zoom {
+                                    Some((_, saved)) => {
+                                        camera = saved;
+                                        None
+                                    },
+                                    None => {
+                                        let center = na::Vector2::new(camera.transform.translation.x, camera.transform.translation.y);
+                                        Some((scene::PanZoomController::new(center, 2.0), camera))
+                                    },
+                                };
+                            }
+                        },
+                        Keycode::Tab if pan_zoom.is_none() => {
+                            if let Event::KeyDown {repeat: false, ..} = event {
+                                fly = match fly {
+                                    Some(_) => {
+                                        orbit = scene::OrbitController::from_camera(&camera, &orbit.target());
+                                        None
+                                    },
+                                    None => Some(scene::FlyController::from_camera(&camera)),
+                                };
+                            }
+                        },
                         _ => input.callback(&event),
                     }
                 },
-                _ => {},
+                _ => input.callback(&event),
             }
         }
-        translation.x = (input.right - input.left) as f32 * 0.02;
-        translation.y = (input.up - input.down) as f32 * 0.02;
 
+        let now = std::time::Instant::now();
+        let elapsed = now - last_frame;
+        let dt = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9;
+        last_frame = now;
+
+        match (pan_zoom.as_mut(), fly.as_mut()) {
+            (Some(pan_zoom), _) => {
+                pan_zoom.0.update(&mut camera, &input, &viewport, dt);
+                translation = na::Vector3::zeros();
+            },
+            (None, Some(fly)) => {
+                fly.update(&mut camera, &input, dt);
+                translation = na::Vector3::zeros();
+            },
+            (None, None) => {
+                orbit.update(&mut camera, &input, dt);
+                translation.x = (input.right - input.left) as f32 * 0.02;
+                translation.y = (input.up - input.down) as f32 * 0.02;
+            },
+        }
+
+        post.begin();
         color_buffer.clear(&gl);
-        &square.update_pos(&translation);
-        square.render(&gl);
+        scene.update_local(square, |t| t.translate(&translation));
+        scene.render(&camera, &viewport);
+        if viewport.fit_mode() != render_gl::FitMode::Stretch {
+            // the passes only draw inside the viewport, so clear the bars around it
+            post.scene().unbind();
+            color_buffer.clear(&gl);
+        }
+        post.finish(&viewport);
         window.gl_swap_window();
     }
 
@@ -0,0 +1,382 @@
+use gl;
+use image::RgbaImage;
+use std::collections::HashMap;
+
+use render_gl::image_data::{ImageData, PixelData};
+use render_gl::texture::{Error, Texture, TextureBuilder};
+use resources::Resources;
+
+/// Where one source image ended up inside a `TextureAtlas`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AtlasRegion {
+    /// Index into `TextureAtlas::pages`.
+    pub page: usize,
+    /// Pixel rectangle on the page, from its top-left corner, without padding or extrusion.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Texture coordinates of the bottom-left and top-right corners.
+    pub uv_min: (f32, f32),
+    pub uv_max: (f32, f32),
+}
+
+pub struct TextureAtlas {
+    pub pages: Vec<Texture>,
+    pub regions: HashMap<String, AtlasRegion>,
+}
+
+impl TextureAtlas {
+    pub fn from_res(gl: &gl::Gl, res: &Resources, names: &[&str]) -> Result<TextureAtlas, Error> {
+        AtlasBuilder::new().from_res(gl, res, names)
+    }
+
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+
+    /// Page texture and region of `name`, ready to bind and draw.
+    pub fn get(&self, name: &str) -> Option<(&Texture, &AtlasRegion)> {
+        self.regions.get(name)
+            .map(|region| (&self.pages[region.page], region))
+    }
+}
+
+/// Packs images into as few pages as possible with a skyline bin packer.
+///
+/// Every image is surrounded by `extrude` pixels copied from its own edges,
+/// so bilinear filtering at the border never reads a neighbour, and
+/// `padding` empty pixels separate the extruded rectangles.
+#[derive(Clone, Debug)]
+pub struct AtlasBuilder {
+    padding: u32,
+    extrude: u32,
+    max_size: u32,
+    texture: TextureBuilder,
+}
+
+impl AtlasBuilder {
+    pub fn new() -> AtlasBuilder {
+        AtlasBuilder {
+            padding: 1,
+            extrude: 1,
+            max_size: 2048,
+            texture: TextureBuilder::new(),
+        }
+    }
+
+    pub fn padding(mut self, padding: u32) -> AtlasBuilder {
+        self.padding = padding;
+        self
+    }
+
+    pub fn extrude(mut self, extrude: u32) -> AtlasBuilder {
+        self.extrude = extrude;
+        self
+    }
+
+    /// Width and height limit of a page; images that do not fit spill to a new page.
+    pub fn max_size(mut self, max_size: u32) -> AtlasBuilder {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Sampling state of the page textures. The pages are composed top row
+    /// first and always flipped on upload, so the flip option is ignored.
+    pub fn texture(mut self, texture: TextureBuilder) -> AtlasBuilder {
+        self.texture = texture;
+        self
+    }
+
+    pub fn from_res(&self, gl: &gl::Gl, res: &Resources, names: &[&str]) -> Result<TextureAtlas, Error> {
+        let mut images = Vec::with_capacity(names.len());
+        for name in names {
+            let img = res.load_image(name)
+                .map_err(|e| Error::ResourceLoad {
+                    name: (*name).into(),
+                    inner: e
+                })?
+                .to_rgba();
+
+            let (w, h) = self.reserved_size(&img);
+            if w > self.max_size || h > self.max_size {
+                return Err(Error::AtlasImageTooLarge {
+                    name: (*name).into(),
+                    width: img.width(),
+                    height: img.height(),
+                    max_size: self.max_size
+                });
+            }
+
+            images.push((*name, img));
+        }
+
+        // tall images first keeps the skyline flat
+        images.sort_by(|a, b| {
+            (b.1.height(), b.1.width()).cmp(&(a.1.height(), a.1.width()))
+        });
+
+        let mut packers: Vec<Skyline> = Vec::new();
+        let mut placements = Vec::with_capacity(images.len());
+        for &(_, ref img) in images.iter() {
+            let (w, h) = self.reserved_size(img);
+
+            let mut placed = None;
+            for (page, packer) in packers.iter_mut().enumerate() {
+                if let Some(pos) = packer.insert(w, h) {
+                    placed = Some((page, pos));
+                    break;
+                }
+            }
+
+            let placed = match placed {
+                Some(placed) => placed,
+                None => {
+                    let mut packer = Skyline::new(self.max_size, self.max_size);
+                    let pos = packer.insert(w, h)
+                        .expect("image fits an empty page");
+                    packers.push(packer);
+                    (packers.len() - 1, pos)
+                },
+            };
+            placements.push(placed);
+        }
+
+        // shrink every page to the used height, rounded up to a power of two
+        let page_sizes = packers.iter()
+            .map(|packer| (self.max_size, packer.used_height().next_power_of_two().min(self.max_size)))
+            .collect::<Vec<_>>();
+
+        let mut pages = page_sizes.iter()
+            .map(|&(w, h)| RgbaImage::new(w, h))
+            .collect::<Vec<_>>();
+
+        let mut regions = HashMap::with_capacity(images.len());
+        for (&(name, ref img), &(page, (x, y))) in images.iter().zip(placements.iter()) {
+            self.blit_extruded(&mut pages[page], img, x, y);
+
+            let (page_w, page_h) = page_sizes[page];
+            let (x, y) = (x + self.extrude, y + self.extrude);
+            let (w, h) = (img.width(), img.height());
+
+            regions.insert(name.to_string(), AtlasRegion {
+                page,
+                x,
+                y,
+                width: w,
+                height: h,
+                uv_min: (x as f32 / page_w as f32, 1.0 - (y + h) as f32 / page_h as f32),
+                uv_max: ((x + w) as f32 / page_w as f32, 1.0 - y as f32 / page_h as f32),
+            });
+        }
+
+        let pages = pages.into_iter()
+            .map(|page| {
+                let mut data = ImageData {
+                    width: page.width(),
+                    height: page.height(),
+                    channels: 4,
+                    bgr: false,
+                    data: PixelData::U8(page.into_raw()),
+                };
+                data.flip_vertically();
+                self.texture.from_image_data(gl, &data)
+            })
+            .collect();
+
+        Ok(TextureAtlas {
+            pages,
+            regions,
+        })
+    }
+
+    fn reserved_size(&self, img: &RgbaImage) -> (u32, u32) {
+        let border = self.extrude * 2 + self.padding;
+        (img.width() + border, img.height() + border)
+    }
+
+    /// Copies the image to `x + extrude, y + extrude` and repeats its
+    /// edge pixels `extrude` times on every side.
+    fn blit_extruded(&self, page: &mut RgbaImage, img: &RgbaImage, x: u32, y: u32) {
+        let e = self.extrude as i64;
+        let (w, h) = (img.width() as i64, img.height() as i64);
+
+        for dy in 0..h + e * 2 {
+            for dx in 0..w + e * 2 {
+                let sx = (dx - e).max(0).min(w - 1) as u32;
+                let sy = (dy - e).max(0).min(h - 1) as u32;
+                page.put_pixel(x + dx as u32, y + dy as u32, *img.get_pixel(sx, sy));
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct SkylineNode {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// Bottom-left skyline packer: keeps the top outline of the placed
+/// rectangles and puts each new one where its top edge ends lowest.
+struct Skyline {
+    width: u32,
+    height: u32,
+    nodes: Vec<SkylineNode>,
+}
+
+impl Skyline {
+    fn new(width: u32, height: u32) -> Skyline {
+        Skyline {
+            width,
+            height,
+            nodes: vec![SkylineNode { x: 0, y: 0, width }],
+        }
+    }
+
+    fn used_height(&self) -> u32 {
+        self.nodes.iter().map(|node| node.y).max().unwrap_or(0).max(1)
+    }
+
+    fn insert(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        let mut best: Option<(usize, u32, u32)> = None;
+        let mut best_top = ::std::u32::MAX;
+        let mut best_width = ::std::u32::MAX;
+
+        for i in 0..self.nodes.len() {
+            if let Some(y) = self.fit(i, w, h) {
+                let top = y + h;
+                if top < best_top || (top == best_top && self.nodes[i].width < best_width) {
+                    best = Some((i, self.nodes[i].x, y));
+                    best_top = top;
+                    best_width = self.nodes[i].width;
+                }
+            }
+        }
+
+        best.map(|(i, x, y)| {
+            self.add_level(i, x, y, w, h);
+            (x, y)
+        })
+    }
+
+    /// Lowest `y` at which a `w * h` rectangle starting at node `i` fits.
+    fn fit(&self, i: usize, w: u32, h: u32) -> Option<u32> {
+        let x = self.nodes[i].x;
+        if x + w > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut width_left = w as i64;
+        let mut j = i;
+        while width_left > 0 {
+            let node = self.nodes.get(j)?;
+            y = y.max(node.y);
+            if y + h > self.height {
+                return None;
+            }
+            width_left -= node.width as i64;
+            j += 1;
+        }
+
+        Some(y)
+    }
+
+    fn add_level(&mut self, i: usize, x: u32, y: u32, w: u32, h: u32) {
+        self.nodes.insert(i, SkylineNode { x, y: y + h, width: w });
+
+        // trim the nodes now covered by the new one
+        let mut j = i + 1;
+        while j < self.nodes.len() {
+            let covered_to = self.nodes[j - 1].x + self.nodes[j - 1].width;
+            if self.nodes[j].x >= covered_to {
+                break;
+            }
+
+            let shrink = covered_to - self.nodes[j].x;
+            if self.nodes[j].width <= shrink {
+                self.nodes.remove(j);
+            } else {
+                self.nodes[j].x += shrink;
+                self.nodes[j].width -= shrink;
+                break;
+            }
+        }
+
+        // merge neighbours at the same height
+        let mut j = 0;
+        while j + 1 < self.nodes.len() {
+            if self.nodes[j].y == self.nodes[j + 1].y {
+                self.nodes[j].width += self.nodes[j + 1].width;
+                self.nodes.remove(j + 1);
+            } else {
+                j += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Skyline;
+
+    fn overlaps(a: (u32, u32, u32, u32), b: (u32, u32, u32, u32)) -> bool {
+        a.0 < b.0 + b.2 && b.0 < a.0 + a.2 && a.1 < b.1 + b.3 && b.1 < a.1 + a.3
+    }
+
+    #[test]
+    fn fills_bottom_row_first() {
+        let mut skyline = Skyline::new(64, 64);
+
+        assert_eq!(skyline.insert(32, 16), Some((0, 0)));
+        assert_eq!(skyline.insert(32, 8), Some((32, 0)));
+        assert_eq!(skyline.insert(32, 8), Some((32, 8)));
+        assert_eq!(skyline.used_height(), 16);
+    }
+
+    #[test]
+    fn fits_exactly_the_page() {
+        let mut skyline = Skyline::new(64, 32);
+
+        assert_eq!(skyline.insert(64, 32), Some((0, 0)));
+        assert_eq!(skyline.insert(1, 1), None);
+    }
+
+    #[test]
+    fn rejects_oversize_images() {
+        let mut skyline = Skyline::new(64, 64);
+
+        assert_eq!(skyline.insert(65, 1), None);
+        assert_eq!(skyline.insert(1, 65), None);
+        assert_eq!(skyline.insert(64, 64), Some((0, 0)));
+    }
+
+    #[test]
+    fn placed_rectangles_do_not_overlap() {
+        let mut skyline = Skyline::new(256, 256);
+        let mut placed = Vec::new();
+
+        // deterministic mix of sizes
+        let mut seed = 12345u32;
+        for _ in 0..200 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let w = 1 + (seed >> 16) % 40;
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let h = 1 + (seed >> 16) % 40;
+
+            if let Some((x, y)) = skyline.insert(w, h) {
+                assert!(x + w <= 256 && y + h <= 256);
+                placed.push((x, y, w, h));
+            }
+        }
+
+        assert!(placed.len() > 20);
+        for (i, &a) in placed.iter().enumerate() {
+            for &b in &placed[i + 1..] {
+                assert!(!overlaps(a, b), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+}
@@ -14,8 +14,26 @@ impl BufferType for ElementArrayType {
     const BUFFER_TYPE: GLuint = gl::ELEMENT_ARRAY_BUFFER;
 }
 
+pub struct ShaderStorageBufferType;
+impl BufferType for ShaderStorageBufferType {
+    const BUFFER_TYPE: GLuint = gl::SHADER_STORAGE_BUFFER;
+}
+
+pub struct DispatchIndirectBufferType;
+impl BufferType for DispatchIndirectBufferType {
+    const BUFFER_TYPE: GLuint = gl::DISPATCH_INDIRECT_BUFFER;
+}
+
+pub struct PixelUnpackBufferType;
+impl BufferType for PixelUnpackBufferType {
+    const BUFFER_TYPE: GLuint = gl::PIXEL_UNPACK_BUFFER;
+}
+
 pub type VertexBuffer = Buffer<VertexBufferType>;
 pub type ElementArray = Buffer<ElementArrayType>;
+pub type ShaderStorageBuffer = Buffer<ShaderStorageBufferType>;
+pub type DispatchIndirectBuffer = Buffer<DispatchIndirectBufferType>;
+pub type PixelUnpackBuffer = Buffer<PixelUnpackBufferType>;
 
 pub struct Buffer<B> where B: BufferType {
     gl: gl::Gl,
@@ -59,6 +77,66 @@ impl<B> Buffer<B> where B: BufferType {
             );
         }
     }
+
+    pub fn dynamic_copy_data<T>(&self, data: &[T]) {
+        unsafe {
+            self.gl.BufferData(
+                B::BUFFER_TYPE,
+                (data.len() * ::std::mem::size_of::<T>()) as GLsizeiptr,
+                data.as_ptr() as *const GLvoid,
+                gl::DYNAMIC_COPY,
+            );
+        }
+    }
+
+    /// Allocates `size` bytes of new storage, dropping the old one. Storage
+    /// still in use by the GPU is released once it is done with it, so this
+    /// does not wait ("orphaning"). The buffer must be bound.
+    pub fn stream_draw_orphan(&self, size: usize) {
+        unsafe {
+            self.gl.BufferData(
+                B::BUFFER_TYPE,
+                size as GLsizeiptr,
+                ::std::ptr::null(),
+                gl::STREAM_DRAW,
+            );
+        }
+    }
+
+    /// Maps the first `size` bytes for writing and passes them to `write`.
+    /// Returns false if the mapping failed or the data was lost while mapped.
+    /// The buffer must be bound.
+    pub fn map_write<F>(&self, size: usize, write: F) -> bool
+        where F: FnOnce(&mut [u8])
+    {
+        unsafe {
+            let ptr = self.gl.MapBufferRange(
+                B::BUFFER_TYPE,
+                0,
+                size as GLsizeiptr,
+                gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_BUFFER_BIT,
+            );
+            if ptr.is_null() {
+                return false;
+            }
+
+            write(::std::slice::from_raw_parts_mut(ptr as *mut u8, size));
+
+            self.gl.UnmapBuffer(B::BUFFER_TYPE) == gl::TRUE
+        }
+    }
+
+    /// Binds the buffer to an indexed binding point of its target,
+    /// e.g. a `layout(binding = N)` storage block.
+    pub fn bind_base(&self, index: u32) {
+        unsafe {
+            self.gl.BindBufferBase(B::BUFFER_TYPE, index as GLuint, self.vbo);
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.vbo
+    }
 }
 
 impl<B> Drop for Buffer<B> where B: BufferType {
@@ -0,0 +1,238 @@
+use nalgebra as na;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Invalid hex color {}, expected #rgb, #rgba, #rrggbb or #rrggbbaa", value)]
+    InvalidHex {
+        value: String
+    },
+}
+
+/// RGBA color with the color channels stored in linear space, where
+/// lighting and blending math is correct. Alpha is always linear.
+///
+/// Colors picked in an image editor, written as hex or given in HSV are
+/// sRGB encoded and converted on the way in and out.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub fn linear(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color { r, g, b, a }
+    }
+
+    pub fn srgb(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color {
+            r: srgb_to_linear(r),
+            g: srgb_to_linear(g),
+            b: srgb_to_linear(b),
+            a,
+        }
+    }
+
+    pub fn srgb8(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color::srgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0)
+    }
+
+    /// `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`; the `#` is optional.
+    pub fn from_hex(hex: &str) -> Result<Color, Error> {
+        let invalid = || Error::InvalidHex { value: hex.into() };
+
+        let digits = hex.trim_left_matches('#');
+        if !digits.chars().all(|c| c.is_digit(16)) {
+            return Err(invalid());
+        }
+
+        let nibble = |i: usize| u8::from_str_radix(&digits[i..i + 1], 16).unwrap();
+        let byte = |i: usize| u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap();
+
+        match digits.len() {
+            3 => Ok(Color::srgb8(nibble(0) * 17, nibble(1) * 17, nibble(2) * 17, 255)),
+            4 => Ok(Color::srgb8(nibble(0) * 17, nibble(1) * 17, nibble(2) * 17, nibble(3) * 17)),
+            6 => Ok(Color::srgb8(byte(0), byte(1), byte(2), 255)),
+            8 => Ok(Color::srgb8(byte(0), byte(1), byte(2), byte(3))),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Hue in degrees, saturation and value in `0..1`, over sRGB encoded channels.
+    pub fn hsv(hue: f32, saturation: f32, value: f32, a: f32) -> Color {
+        let h = (hue % 360.0 + 360.0) % 360.0 / 60.0;
+        let c = value * saturation;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let m = value - c;
+
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+
+        Color::srgb(r + m, g + m, b + m, a)
+    }
+
+    /// sRGB encoded `[r, g, b, a]`.
+    pub fn to_srgb(&self) -> [f32; 4] {
+        [linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b), self.a]
+    }
+
+    pub fn to_srgb8(&self) -> [u8; 4] {
+        let srgb = self.to_srgb();
+        let to_u8 = |c: f32| (c.max(0.0).min(1.0) * 255.0).round() as u8;
+        [to_u8(srgb[0]), to_u8(srgb[1]), to_u8(srgb[2]), to_u8(srgb[3])]
+    }
+
+    /// `(hue in degrees, saturation, value)` of the sRGB encoded color.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let srgb = self.to_srgb();
+        let (r, g, b) = (srgb[0], srgb[1], srgb[2]);
+
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta % 6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+
+        let saturation = if max == 0.0 { 0.0 } else { delta / max };
+        ((hue + 360.0) % 360.0, saturation, max)
+    }
+
+    /// `#rrggbb`, or `#rrggbbaa` if not opaque.
+    pub fn to_hex(&self) -> String {
+        let c = self.to_srgb8();
+        if c[3] == 255 {
+            format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2])
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", c[0], c[1], c[2], c[3])
+        }
+    }
+
+    pub fn with_alpha(mut self, a: f32) -> Color {
+        self.a = a;
+        self
+    }
+
+    /// Color channels multiplied by alpha, for `BlendState::premultiplied_alpha`.
+    pub fn premultiplied(&self) -> Color {
+        Color::linear(self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+
+    /// Interpolates in linear space, which keeps gradients from darkening in the middle.
+    pub fn lerp(&self, other: &Color, t: f32) -> Color {
+        Color::linear(
+            self.r + (other.r - self.r) * t,
+            self.g + (other.g - self.g) * t,
+            self.b + (other.b - self.b) * t,
+            self.a + (other.a - self.a) * t,
+        )
+    }
+}
+
+impl From<Color> for na::Vector4<f32> {
+    fn from(color: Color) -> Self {
+        na::Vector4::new(color.r, color.g, color.b, color.a)
+    }
+}
+
+impl From<na::Vector4<f32>> for Color {
+    fn from(v: na::Vector4<f32>) -> Self {
+        Color::linear(v.x, v.y, v.z, v.w)
+    }
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{linear_to_srgb, srgb_to_linear, Color};
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        for hex in &["#336699", "#000000", "#ffffff", "#0a1b2c80"] {
+            assert_eq!(Color::from_hex(hex).unwrap().to_hex(), *hex);
+        }
+    }
+
+    #[test]
+    fn hex_short_forms() {
+        assert_eq!(Color::from_hex("#369").unwrap().to_hex(), "#336699");
+        assert_eq!(Color::from_hex("369").unwrap().to_hex(), "#336699");
+        assert_eq!(Color::from_hex("#3698").unwrap().to_hex(), "#33669988");
+    }
+
+    #[test]
+    fn hex_rejects_invalid() {
+        assert!(Color::from_hex("#12345").is_err());
+        assert!(Color::from_hex("#ggg").is_err());
+        assert!(Color::from_hex("").is_err());
+    }
+
+    #[test]
+    fn srgb_linear_round_trip() {
+        for i in 0..=255 {
+            let c = i as f32 / 255.0;
+            assert_close(linear_to_srgb(srgb_to_linear(c)), c);
+        }
+
+        assert_close(srgb_to_linear(0.5), 0.21404);
+        assert_close(linear_to_srgb(0.5), 0.73536);
+    }
+
+    #[test]
+    fn srgb8_round_trip() {
+        let color = Color::srgb8(51, 102, 153, 255);
+        assert_eq!(color.to_srgb8(), [51, 102, 153, 255]);
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        for &(h, s, v) in &[(0.0, 1.0, 1.0), (120.0, 0.5, 0.8), (210.0, 0.667, 0.6), (300.0, 0.25, 0.4)] {
+            let (h2, s2, v2) = Color::hsv(h, s, v, 1.0).to_hsv();
+            assert_close(h2, h);
+            assert_close(s2, s);
+            assert_close(v2, v);
+        }
+    }
+
+    #[test]
+    fn hsv_primaries() {
+        assert_eq!(Color::hsv(0.0, 1.0, 1.0, 1.0).to_hex(), "#ff0000");
+        assert_eq!(Color::hsv(120.0, 1.0, 1.0, 1.0).to_hex(), "#00ff00");
+        assert_eq!(Color::hsv(240.0, 1.0, 1.0, 1.0).to_hex(), "#0000ff");
+        assert_eq!(Color::hsv(360.0, 1.0, 1.0, 1.0).to_hex(), "#ff0000");
+    }
+}
@@ -1,11 +1,21 @@
 use gl;
 use nalgebra as na;
 
+use render_gl::color::Color;
+
+/// Clear color in linear space; with `FRAMEBUFFER_SRGB` enabled GL encodes
+/// it when writing to an sRGB framebuffer.
 pub struct ColorBuffer {
     pub color: na::Vector4<f32>,
 }
 
 impl ColorBuffer {
+    pub fn new(color: Color) -> ColorBuffer {
+        ColorBuffer {
+            color: color.into(),
+        }
+    }
+
     pub fn from_color(color: na::Vector3<f32>) -> ColorBuffer {
         ColorBuffer {
             color: color.fixed_resize::<na::U4, na::U1>(1.0),
@@ -16,9 +26,13 @@ impl ColorBuffer {
         self.color = color.fixed_resize::<na::U4, na::U1>(1.0);
     }
 
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color.into();
+    }
+
     pub fn enable(&self, gl: &gl::Gl) {
         unsafe {
-            gl.ClearColor(self.color.x, self.color.y, self.color.z, 1.0);
+            gl.ClearColor(self.color.x, self.color.y, self.color.z, self.color.w);
         }
     }
 
@@ -27,4 +41,14 @@ impl ColorBuffer {
             gl.Clear(gl::COLOR_BUFFER_BIT);
         }
     }
+
+    /// Clears color, depth to 1.0 and stencil to 0. Write masks and the
+    /// scissor rectangle of the current `RenderState` still apply.
+    pub fn clear_all(&self, gl: &gl::Gl) {
+        unsafe {
+            gl.ClearDepth(1.0);
+            gl.ClearStencil(0);
+            gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,668 @@
+use gl;
+use gl::types::{GLenum, GLint, GLsizei, GLuint, GLvoid};
+
+use render_gl::layered_texture::{Texture2DArray, TextureCube};
+use render_gl::texture::{Error, Texture, TextureBuilder};
+use resources::Resources;
+
+/// Block compressed formats that can be read from KTX2 and DDS files.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CompressedFormat {
+    Bc1Rgb,
+    Bc1Rgba,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc4Signed,
+    Bc5,
+    Bc5Signed,
+    Bc6hUnsigned,
+    Bc6hSigned,
+    Bc7,
+    Etc2Rgb8,
+    Etc2Rgb8A1,
+    Etc2Rgba8,
+    EacR11,
+    EacR11Signed,
+    EacRg11,
+    EacRg11Signed,
+}
+
+impl CompressedFormat {
+    /// Bytes per 4x4 block.
+    pub fn block_bytes(&self) -> usize {
+        match *self {
+            CompressedFormat::Bc1Rgb
+            | CompressedFormat::Bc1Rgba
+            | CompressedFormat::Bc4
+            | CompressedFormat::Bc4Signed
+            | CompressedFormat::Etc2Rgb8
+            | CompressedFormat::Etc2Rgb8A1
+            | CompressedFormat::EacR11
+            | CompressedFormat::EacR11Signed => 8,
+            _ => 16,
+        }
+    }
+
+    /// Formats without an sRGB variant ignore `srgb`.
+    pub fn gl_internal_format(&self, srgb: bool) -> GLenum {
+        match (*self, srgb) {
+            (CompressedFormat::Bc1Rgb, false) => gl::COMPRESSED_RGB_S3TC_DXT1_EXT,
+            (CompressedFormat::Bc1Rgb, true) => gl::COMPRESSED_SRGB_S3TC_DXT1_EXT,
+            (CompressedFormat::Bc1Rgba, false) => gl::COMPRESSED_RGBA_S3TC_DXT1_EXT,
+            (CompressedFormat::Bc1Rgba, true) => gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT,
+            (CompressedFormat::Bc2, false) => gl::COMPRESSED_RGBA_S3TC_DXT3_EXT,
+            (CompressedFormat::Bc2, true) => gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT,
+            (CompressedFormat::Bc3, false) => gl::COMPRESSED_RGBA_S3TC_DXT5_EXT,
+            (CompressedFormat::Bc3, true) => gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT,
+            (CompressedFormat::Bc4, _) => gl::COMPRESSED_RED_RGTC1,
+            (CompressedFormat::Bc4Signed, _) => gl::COMPRESSED_SIGNED_RED_RGTC1,
+            (CompressedFormat::Bc5, _) => gl::COMPRESSED_RG_RGTC2,
+            (CompressedFormat::Bc5Signed, _) => gl::COMPRESSED_SIGNED_RG_RGTC2,
+            (CompressedFormat::Bc6hUnsigned, _) => gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+            (CompressedFormat::Bc6hSigned, _) => gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT,
+            (CompressedFormat::Bc7, false) => gl::COMPRESSED_RGBA_BPTC_UNORM,
+            (CompressedFormat::Bc7, true) => gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+            (CompressedFormat::Etc2Rgb8, false) => gl::COMPRESSED_RGB8_ETC2,
+            (CompressedFormat::Etc2Rgb8, true) => gl::COMPRESSED_SRGB8_ETC2,
+            (CompressedFormat::Etc2Rgb8A1, false) => gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            (CompressedFormat::Etc2Rgb8A1, true) => gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            (CompressedFormat::Etc2Rgba8, false) => gl::COMPRESSED_RGBA8_ETC2_EAC,
+            (CompressedFormat::Etc2Rgba8, true) => gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+            (CompressedFormat::EacR11, _) => gl::COMPRESSED_R11_EAC,
+            (CompressedFormat::EacR11Signed, _) => gl::COMPRESSED_SIGNED_R11_EAC,
+            (CompressedFormat::EacRg11, _) => gl::COMPRESSED_RG11_EAC,
+            (CompressedFormat::EacRg11Signed, _) => gl::COMPRESSED_SIGNED_RG11_EAC,
+        }
+    }
+
+    /// Byte size of one layer of a `width * height` mip level.
+    pub fn level_size(&self, width: u32, height: u32) -> usize {
+        let blocks_x = ((width as usize + 3) / 4).max(1);
+        let blocks_y = ((height as usize + 3) / 4).max(1);
+        blocks_x * blocks_y * self.block_bytes()
+    }
+
+    fn from_vk_format(vk_format: u32) -> Option<(CompressedFormat, bool)> {
+        let format = match vk_format {
+            131 => (CompressedFormat::Bc1Rgb, false),
+            132 => (CompressedFormat::Bc1Rgb, true),
+            133 => (CompressedFormat::Bc1Rgba, false),
+            134 => (CompressedFormat::Bc1Rgba, true),
+            135 => (CompressedFormat::Bc2, false),
+            136 => (CompressedFormat::Bc2, true),
+            137 => (CompressedFormat::Bc3, false),
+            138 => (CompressedFormat::Bc3, true),
+            139 => (CompressedFormat::Bc4, false),
+            140 => (CompressedFormat::Bc4Signed, false),
+            141 => (CompressedFormat::Bc5, false),
+            142 => (CompressedFormat::Bc5Signed, false),
+            143 => (CompressedFormat::Bc6hUnsigned, false),
+            144 => (CompressedFormat::Bc6hSigned, false),
+            145 => (CompressedFormat::Bc7, false),
+            146 => (CompressedFormat::Bc7, true),
+            147 => (CompressedFormat::Etc2Rgb8, false),
+            148 => (CompressedFormat::Etc2Rgb8, true),
+            149 => (CompressedFormat::Etc2Rgb8A1, false),
+            150 => (CompressedFormat::Etc2Rgb8A1, true),
+            151 => (CompressedFormat::Etc2Rgba8, false),
+            152 => (CompressedFormat::Etc2Rgba8, true),
+            153 => (CompressedFormat::EacR11, false),
+            154 => (CompressedFormat::EacR11Signed, false),
+            155 => (CompressedFormat::EacRg11, false),
+            156 => (CompressedFormat::EacRg11Signed, false),
+            _ => return None,
+        };
+
+        Some(format)
+    }
+
+    fn from_dxgi_format(dxgi_format: u32) -> Option<(CompressedFormat, bool)> {
+        let format = match dxgi_format {
+            71 => (CompressedFormat::Bc1Rgba, false),
+            72 => (CompressedFormat::Bc1Rgba, true),
+            74 => (CompressedFormat::Bc2, false),
+            75 => (CompressedFormat::Bc2, true),
+            77 => (CompressedFormat::Bc3, false),
+            78 => (CompressedFormat::Bc3, true),
+            80 => (CompressedFormat::Bc4, false),
+            81 => (CompressedFormat::Bc4Signed, false),
+            83 => (CompressedFormat::Bc5, false),
+            84 => (CompressedFormat::Bc5Signed, false),
+            95 => (CompressedFormat::Bc6hUnsigned, false),
+            96 => (CompressedFormat::Bc6hSigned, false),
+            98 => (CompressedFormat::Bc7, false),
+            99 => (CompressedFormat::Bc7, true),
+            _ => return None,
+        };
+
+        Some(format)
+    }
+
+    fn from_four_cc(four_cc: &[u8]) -> Option<CompressedFormat> {
+        let format = match four_cc {
+            b"DXT1" => CompressedFormat::Bc1Rgba,
+            b"DXT2" | b"DXT3" => CompressedFormat::Bc2,
+            b"DXT4" | b"DXT5" => CompressedFormat::Bc3,
+            b"ATI1" | b"BC4U" => CompressedFormat::Bc4,
+            b"BC4S" => CompressedFormat::Bc4Signed,
+            b"ATI2" | b"BC5U" => CompressedFormat::Bc5,
+            b"BC5S" => CompressedFormat::Bc5Signed,
+            _ => return None,
+        };
+
+        Some(format)
+    }
+}
+
+/// Compressed texture as stored in a KTX2 or DDS file.
+///
+/// `levels[i]` holds mip level `i` of every layer, layer after layer; cube
+/// maps store their six faces as consecutive layers in GL face order.
+#[derive(Clone, Debug)]
+pub struct CompressedImage {
+    pub format: CompressedFormat,
+    pub srgb: bool,
+    pub width: u32,
+    pub height: u32,
+    pub layers: u32,
+    pub cube: bool,
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl CompressedImage {
+    /// Parses `.ktx2` and `.dds` resources.
+    pub fn from_res(res: &Resources, name: &str) -> Result<CompressedImage, Error> {
+        let bytes = res.load_bytes(name)
+            .map_err(|e| Error::ResourceLoad {
+                name: name.into(),
+                inner: e
+            })?;
+
+        let parsed = if name.ends_with(".ktx2") {
+            parse_ktx2(&bytes)
+        } else if name.ends_with(".dds") {
+            parse_dds(&bytes)
+        } else {
+            Err(String::from("expected a .ktx2 or .dds file"))
+        };
+
+        parsed.map_err(|message| Error::InvalidContainer {
+            name: name.into(),
+            message
+        })
+    }
+
+    pub fn level_width(&self, level: usize) -> u32 {
+        (self.width >> level).max(1)
+    }
+
+    pub fn level_height(&self, level: usize) -> u32 {
+        (self.height >> level).max(1)
+    }
+
+    /// Number of 2D images per level: array layers, times 6 for cube maps.
+    pub fn images_per_level(&self) -> u32 {
+        if self.cube { self.layers * 6 } else { self.layers }
+    }
+
+    fn image(&self, level: usize, index: u32) -> &[u8] {
+        let size = self.format.level_size(self.level_width(level), self.level_height(level));
+        let start = index as usize * size;
+        &self.levels[level][start..start + size]
+    }
+}
+
+impl TextureBuilder {
+    /// Uploads a single-layer KTX2/DDS file with its stored mip chain. When the
+    /// driver lacks the format, BC1-BC3 are decompressed to RGBA8 on the CPU;
+    /// the other formats are core in GL 4.5 and need no fallback.
+    /// The result does not support `Texture::sub_image`.
+    pub fn compressed_from_res(&self, gl: &gl::Gl, res: &Resources, name: &str) -> Result<Texture, Error> {
+        let img = CompressedImage::from_res(res, name)?;
+        if img.layers != 1 || img.cube {
+            return Err(Error::InvalidContainer {
+                name: name.into(),
+                message: String::from("expected a single 2D image, use the array or cube loader")
+            });
+        }
+
+        let id = self.upload_compressed(gl, name, &img, gl::TEXTURE_2D)?;
+        Ok(Texture::from_raw(gl, id, img.width, img.height, None))
+    }
+
+    pub fn compressed_array_from_res(&self, gl: &gl::Gl, res: &Resources, name: &str) -> Result<Texture2DArray, Error> {
+        let img = CompressedImage::from_res(res, name)?;
+        if img.cube {
+            return Err(Error::InvalidContainer {
+                name: name.into(),
+                message: String::from("cube map given where a 2D array was expected")
+            });
+        }
+
+        let id = self.upload_compressed(gl, name, &img, gl::TEXTURE_2D_ARRAY)?;
+        Ok(Texture2DArray::from_raw(gl, id, img.width, img.height, img.layers))
+    }
+
+    pub fn compressed_cube_from_res(&self, gl: &gl::Gl, res: &Resources, name: &str) -> Result<TextureCube, Error> {
+        let img = CompressedImage::from_res(res, name)?;
+        if !img.cube || img.layers != 1 {
+            return Err(Error::InvalidContainer {
+                name: name.into(),
+                message: String::from("expected a single cube map")
+            });
+        }
+
+        let id = self.upload_compressed(gl, name, &img, gl::TEXTURE_CUBE_MAP)?;
+        Ok(TextureCube::from_raw(gl, id, img.width, img.height, 6))
+    }
+
+    fn upload_compressed(&self, gl: &gl::Gl, name: &str, img: &CompressedImage, target: GLenum) -> Result<GLuint, Error> {
+        let internal_format = img.format.gl_internal_format(img.srgb);
+        let supported = is_format_supported(gl, target, internal_format);
+
+        if !supported && decompress_block_fn(img.format).is_none() {
+            return Err(Error::UnsupportedFormat {
+                name: name.into(),
+                format: img.format
+            });
+        }
+
+        let mut texture_id: GLuint = 0;
+        unsafe {
+            gl.GenTextures(1, &mut texture_id);
+            gl.BindTexture(target, texture_id);
+            gl.TexParameteri(target, gl::TEXTURE_BASE_LEVEL, 0);
+            gl.TexParameteri(target, gl::TEXTURE_MAX_LEVEL, img.levels.len() as GLint - 1);
+        }
+
+        self.apply_parameters(gl, target);
+
+        for level in 0..img.levels.len() {
+            if supported {
+                upload_compressed_level(gl, target, img, level, internal_format);
+            } else {
+                upload_decompressed_level(gl, target, img, level);
+            }
+        }
+
+        unsafe {
+            gl.BindTexture(target, 0);
+        }
+
+        Ok(texture_id)
+    }
+}
+
+fn is_format_supported(gl: &gl::Gl, target: GLenum, internal_format: GLenum) -> bool {
+    let mut supported: GLint = 0;
+    unsafe {
+        gl.GetInternalformativ(target, internal_format, gl::INTERNALFORMAT_SUPPORTED, 1, &mut supported);
+    }
+
+    supported == gl::TRUE as GLint
+}
+
+fn upload_compressed_level(gl: &gl::Gl, target: GLenum, img: &CompressedImage, level: usize, internal_format: GLenum) {
+    let width = img.level_width(level) as GLsizei;
+    let height = img.level_height(level) as GLsizei;
+
+    unsafe {
+        match target {
+            gl::TEXTURE_2D_ARRAY => {
+                let data = &img.levels[level];
+                gl.CompressedTexImage3D(
+                    target, level as GLint, internal_format,
+                    width, height, img.layers as GLsizei, 0,
+                    data.len() as GLsizei, data.as_ptr() as *const GLvoid
+                );
+            },
+            gl::TEXTURE_CUBE_MAP => {
+                for face in 0..6 {
+                    let data = img.image(level, face);
+                    gl.CompressedTexImage2D(
+                        gl::TEXTURE_CUBE_MAP_POSITIVE_X + face, level as GLint, internal_format,
+                        width, height, 0,
+                        data.len() as GLsizei, data.as_ptr() as *const GLvoid
+                    );
+                }
+            },
+            _ => {
+                let data = img.image(level, 0);
+                gl.CompressedTexImage2D(
+                    target, level as GLint, internal_format,
+                    width, height, 0,
+                    data.len() as GLsizei, data.as_ptr() as *const GLvoid
+                );
+            },
+        }
+    }
+}
+
+fn upload_decompressed_level(gl: &gl::Gl, target: GLenum, img: &CompressedImage, level: usize) {
+    let width = img.level_width(level);
+    let height = img.level_height(level);
+    let internal_format = if img.srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 };
+
+    let images = (0..img.images_per_level())
+        .map(|index| decompress(img.format, img.image(level, index), width, height))
+        .collect::<Vec<_>>();
+
+    unsafe {
+        match target {
+            gl::TEXTURE_2D_ARRAY => {
+                let data = images.concat();
+                gl.TexImage3D(
+                    target, level as GLint, internal_format as GLint,
+                    width as GLsizei, height as GLsizei, img.layers as GLsizei, 0,
+                    gl::RGBA, gl::UNSIGNED_BYTE, data.as_ptr() as *const GLvoid
+                );
+            },
+            gl::TEXTURE_CUBE_MAP => {
+                for (face, data) in images.iter().enumerate() {
+                    gl.TexImage2D(
+                        gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as GLenum, level as GLint, internal_format as GLint,
+                        width as GLsizei, height as GLsizei, 0,
+                        gl::RGBA, gl::UNSIGNED_BYTE, data.as_ptr() as *const GLvoid
+                    );
+                }
+            },
+            _ => {
+                gl.TexImage2D(
+                    target, level as GLint, internal_format as GLint,
+                    width as GLsizei, height as GLsizei, 0,
+                    gl::RGBA, gl::UNSIGNED_BYTE, images[0].as_ptr() as *const GLvoid
+                );
+            },
+        }
+    }
+}
+
+// ---- containers ----
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, String> {
+    bytes.get(offset..offset + 4)
+        .map(|b| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+        .ok_or_else(|| format!("unexpected end of file at byte {}", offset))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, String> {
+    let low = read_u32(bytes, offset)? as u64;
+    let high = read_u32(bytes, offset + 4)? as u64;
+    Ok(low | high << 32)
+}
+
+fn slice(bytes: &[u8], offset: usize, len: usize) -> Result<&[u8], String> {
+    offset.checked_add(len)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or_else(|| format!("data of {} bytes at byte {} is past the end of file", len, offset))
+}
+
+/// Checks a level count from a file header against the full mip chain of
+/// a `width` x `height` image, so corrupt headers cannot ask for huge
+/// allocations or shift sizes by 32 bits or more.
+fn check_level_count(level_count: u32, width: u32, height: u32) -> Result<u32, String> {
+    let max_levels = 32 - width.max(height).leading_zeros();
+    if level_count > max_levels {
+        return Err(format!("{} mip levels given for a {}x{} image, at most {} possible",
+            level_count, width, height, max_levels));
+    }
+    Ok(level_count)
+}
+
+fn parse_ktx2(bytes: &[u8]) -> Result<CompressedImage, String> {
+    const IDENTIFIER: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+    const LEVEL_INDEX_OFFSET: usize = 80;
+
+    if bytes.len() < LEVEL_INDEX_OFFSET || bytes[..12] != IDENTIFIER {
+        return Err(String::from("missing KTX2 identifier"));
+    }
+
+    let vk_format = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 20)?;
+    let height = read_u32(bytes, 24)?;
+    let depth = read_u32(bytes, 28)?;
+    let layer_count = read_u32(bytes, 32)?;
+    let face_count = read_u32(bytes, 36)?;
+    let level_count = read_u32(bytes, 40)?;
+    let supercompression = read_u32(bytes, 44)?;
+
+    let (format, srgb) = CompressedFormat::from_vk_format(vk_format)
+        .ok_or_else(|| format!("unsupported vkFormat {}", vk_format))?;
+
+    if supercompression != 0 {
+        return Err(format!("supercompression scheme {} is not supported", supercompression));
+    }
+    if depth > 1 {
+        return Err(String::from("3D textures are not supported"));
+    }
+    if face_count != 1 && face_count != 6 {
+        return Err(format!("invalid face count {}", face_count));
+    }
+
+    let layers = layer_count.max(1);
+    let cube = face_count == 6;
+    let images_per_level = layers.checked_mul(face_count)
+        .ok_or_else(|| format!("{} layers of {} faces is too many", layers, face_count))?;
+
+    // a level count of 0 asks the loader to generate mipmaps,
+    // which is not possible for compressed data
+    let level_count = check_level_count(level_count.max(1), width, height)?;
+    let mut levels = Vec::new();
+    for level in 0..level_count as usize {
+        let entry = LEVEL_INDEX_OFFSET + level * 24;
+        let offset = read_u64(bytes, entry)? as usize;
+        let len = read_u64(bytes, entry + 8)? as usize;
+
+        let expected = format.level_size((width >> level).max(1), (height >> level).max(1))
+            .checked_mul(images_per_level as usize)
+            .ok_or_else(|| format!("level {} is too large", level))?;
+        if len != expected {
+            return Err(format!("level {} has {} bytes, expected {}", level, len, expected));
+        }
+
+        levels.push(slice(bytes, offset, len)?.to_vec());
+    }
+
+    Ok(CompressedImage {
+        format,
+        srgb,
+        width,
+        height,
+        layers,
+        cube,
+        levels,
+    })
+}
+
+fn parse_dds(bytes: &[u8]) -> Result<CompressedImage, String> {
+    const HEADER_END: usize = 128;
+    const DX10_HEADER_END: usize = 148;
+    const DDSCAPS2_CUBEMAP: u32 = 0x200;
+    const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+    if bytes.len() < HEADER_END || &bytes[..4] != b"DDS " {
+        return Err(String::from("missing DDS magic"));
+    }
+
+    let height = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 16)?;
+    let level_count = check_level_count(read_u32(bytes, 28)?.max(1), width, height)?;
+    let four_cc = slice(bytes, 84, 4)?;
+    let caps2 = read_u32(bytes, 112)?;
+
+    let (format, srgb, layers, cube, data_start) = if four_cc == b"DX10" {
+        let dxgi_format = read_u32(bytes, 128)?;
+        let misc_flag = read_u32(bytes, 136)?;
+        let array_size = read_u32(bytes, 140)?.max(1);
+
+        let (format, srgb) = CompressedFormat::from_dxgi_format(dxgi_format)
+            .ok_or_else(|| format!("unsupported DXGI format {}", dxgi_format))?;
+
+        (format, srgb, array_size, misc_flag & DDS_RESOURCE_MISC_TEXTURECUBE != 0, DX10_HEADER_END)
+    } else {
+        let format = CompressedFormat::from_four_cc(four_cc)
+            .ok_or_else(|| format!("unsupported FourCC {}", String::from_utf8_lossy(four_cc)))?;
+
+        (format, false, 1, caps2 & DDSCAPS2_CUBEMAP != 0, HEADER_END)
+    };
+
+    let images = if cube {
+        layers.checked_mul(6).ok_or_else(|| format!("{} cube maps is too many", layers))?
+    } else {
+        layers
+    };
+
+    // DDS stores each image with its full mip chain, one image after another;
+    // regroup the data per level
+    let mut levels = vec![Vec::new(); level_count as usize];
+    let mut offset = data_start;
+    for _ in 0..images {
+        for (level, data) in levels.iter_mut().enumerate() {
+            let len = format.level_size((width >> level).max(1), (height >> level).max(1));
+            data.extend_from_slice(slice(bytes, offset, len)?);
+            offset += len;
+        }
+    }
+
+    Ok(CompressedImage {
+        format,
+        srgb,
+        width,
+        height,
+        layers,
+        cube,
+        levels,
+    })
+}
+
+// ---- CPU fallback ----
+
+type DecompressBlockFn = fn(&[u8], &mut [[u8; 4]; 16]);
+
+fn decompress_block_fn(format: CompressedFormat) -> Option<DecompressBlockFn> {
+    match format {
+        CompressedFormat::Bc1Rgb => Some(decompress_bc1_rgb as DecompressBlockFn),
+        CompressedFormat::Bc1Rgba => Some(decompress_bc1 as DecompressBlockFn),
+        CompressedFormat::Bc2 => Some(decompress_bc2 as DecompressBlockFn),
+        CompressedFormat::Bc3 => Some(decompress_bc3 as DecompressBlockFn),
+        _ => None,
+    }
+}
+
+/// Decodes one layer of a level into tightly packed RGBA8.
+fn decompress(format: CompressedFormat, data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let decompress_block = decompress_block_fn(format)
+        .expect("format has no CPU decoder");
+
+    let (width, height) = (width as usize, height as usize);
+    let blocks_x = ((width + 3) / 4).max(1);
+    let block_bytes = format.block_bytes();
+
+    let mut rgba = vec![0u8; width * height * 4];
+    let mut texels = [[0u8; 4]; 16];
+
+    for (i, block) in data.chunks(block_bytes).enumerate() {
+        decompress_block(block, &mut texels);
+
+        let (bx, by) = ((i % blocks_x) * 4, (i / blocks_x) * 4);
+        for ty in 0..4 {
+            for tx in 0..4 {
+                let (x, y) = (bx + tx, by + ty);
+                if x < width && y < height {
+                    let dst = (y * width + x) * 4;
+                    rgba[dst..dst + 4].copy_from_slice(&texels[ty * 4 + tx]);
+                }
+            }
+        }
+    }
+
+    rgba
+}
+
+fn rgb565(color: u16) -> [u8; 3] {
+    let r = (color >> 11) & 0x1f;
+    let g = (color >> 5) & 0x3f;
+    let b = color & 0x1f;
+    [
+        (r << 3 | r >> 2) as u8,
+        (g << 2 | g >> 4) as u8,
+        (b << 3 | b >> 2) as u8,
+    ]
+}
+
+fn mix(a: [u8; 3], b: [u8; 3], wa: u32, wb: u32) -> [u8; 4] {
+    let total = wa + wb;
+    [
+        ((a[0] as u32 * wa + b[0] as u32 * wb) / total) as u8,
+        ((a[1] as u32 * wa + b[1] as u32 * wb) / total) as u8,
+        ((a[2] as u32 * wa + b[2] as u32 * wb) / total) as u8,
+        255,
+    ]
+}
+
+/// Color part shared by BC1-BC3. BC2 and BC3 always use the four color mode.
+fn decompress_color_block(block: &[u8], four_color_only: bool, texels: &mut [[u8; 4]; 16]) {
+    let c0 = block[0] as u16 | (block[1] as u16) << 8;
+    let c1 = block[2] as u16 | (block[3] as u16) << 8;
+    let (rgb0, rgb1) = (rgb565(c0), rgb565(c1));
+
+    let palette = if c0 > c1 || four_color_only {
+        [mix(rgb0, rgb1, 1, 0), mix(rgb0, rgb1, 0, 1), mix(rgb0, rgb1, 2, 1), mix(rgb0, rgb1, 1, 2)]
+    } else {
+        [mix(rgb0, rgb1, 1, 0), mix(rgb0, rgb1, 0, 1), mix(rgb0, rgb1, 1, 1), [0, 0, 0, 0]]
+    };
+
+    let indices = block[4] as u32 | (block[5] as u32) << 8 | (block[6] as u32) << 16 | (block[7] as u32) << 24;
+    for (i, texel) in texels.iter_mut().enumerate() {
+        *texel = palette[(indices >> (i * 2) & 0x3) as usize];
+    }
+}
+
+fn decompress_bc1(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decompress_color_block(block, false, texels);
+}
+
+/// BC1 without punch-through alpha: the transparent black entry is opaque.
+fn decompress_bc1_rgb(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decompress_color_block(block, false, texels);
+
+    for texel in texels.iter_mut() {
+        texel[3] = 255;
+    }
+}
+
+fn decompress_bc2(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decompress_color_block(&block[8..], true, texels);
+
+    for (i, texel) in texels.iter_mut().enumerate() {
+        let alpha = (block[i / 2] >> ((i % 2) * 4)) & 0xf;
+        texel[3] = alpha << 4 | alpha;
+    }
+}
+
+fn decompress_bc3(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decompress_color_block(&block[8..], true, texels);
+
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let mut alphas = [0u8; 8];
+    alphas[0] = a0 as u8;
+    alphas[1] = a1 as u8;
+    if a0 > a1 {
+        for i in 1..7 {
+            alphas[i + 1] = ((a0 * (7 - i as u32) + a1 * i as u32) / 7) as u8;
+        }
+    } else {
+        for i in 1..5 {
+            alphas[i + 1] = ((a0 * (5 - i as u32) + a1 * i as u32) / 5) as u8;
+        }
+        alphas[6] = 0;
+        alphas[7] = 255;
+    }
+
+    let bits = block[2..8].iter().rev()
+        .fold(0u64, |bits, &byte| bits << 8 | byte as u64);
+    for (i, texel) in texels.iter_mut().enumerate() {
+        texel[3] = alphas[(bits >> (i * 3) & 0x7) as usize];
+    }
+}
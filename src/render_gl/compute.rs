@@ -0,0 +1,187 @@
+use gl;
+use gl::types::{GLbitfield, GLboolean, GLenum, GLint, GLuint};
+use std::ffi::CString;
+use std::ops::BitOr;
+
+use render_gl::buffer::{Buffer, BufferType, DispatchIndirectBuffer};
+use render_gl::shader::{Program, Error};
+use render_gl::texture::Texture;
+
+/// Set of `MemoryBarrier` bits. Pick the bits by how the data written
+/// by a dispatch is consumed next, not by how it was written.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Barrier {
+    bits: GLbitfield,
+}
+
+impl Barrier {
+    /// Buffer is read next as vertex attributes (e.g. simulated particles).
+    pub const VERTEX_ATTRIB_ARRAY: Barrier = Barrier { bits: gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT };
+    pub const ELEMENT_ARRAY: Barrier = Barrier { bits: gl::ELEMENT_ARRAY_BARRIER_BIT };
+    pub const UNIFORM: Barrier = Barrier { bits: gl::UNIFORM_BARRIER_BIT };
+    /// Image is sampled next through a regular `sampler*` uniform.
+    pub const TEXTURE_FETCH: Barrier = Barrier { bits: gl::TEXTURE_FETCH_BARRIER_BIT };
+    /// Image is read or written next through `image*` uniforms.
+    pub const SHADER_IMAGE_ACCESS: Barrier = Barrier { bits: gl::SHADER_IMAGE_ACCESS_BARRIER_BIT };
+    /// Buffer is read next as draw or dispatch indirect commands (e.g. culling output).
+    pub const COMMAND: Barrier = Barrier { bits: gl::COMMAND_BARRIER_BIT };
+    pub const PIXEL_BUFFER: Barrier = Barrier { bits: gl::PIXEL_BUFFER_BARRIER_BIT };
+    pub const TEXTURE_UPDATE: Barrier = Barrier { bits: gl::TEXTURE_UPDATE_BARRIER_BIT };
+    pub const BUFFER_UPDATE: Barrier = Barrier { bits: gl::BUFFER_UPDATE_BARRIER_BIT };
+    pub const FRAMEBUFFER: Barrier = Barrier { bits: gl::FRAMEBUFFER_BARRIER_BIT };
+    /// Buffer is read or written next through a storage block.
+    pub const SHADER_STORAGE: Barrier = Barrier { bits: gl::SHADER_STORAGE_BARRIER_BIT };
+    pub const ALL: Barrier = Barrier { bits: gl::ALL_BARRIER_BITS };
+
+    pub fn bits(&self) -> GLbitfield {
+        self.bits
+    }
+
+    pub fn issue(&self, gl: &gl::Gl) {
+        unsafe {
+            gl.MemoryBarrier(self.bits);
+        }
+    }
+}
+
+impl BitOr for Barrier {
+    type Output = Barrier;
+
+    fn bitor(self, other: Barrier) -> Barrier {
+        Barrier { bits: self.bits | other.bits }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageAccess {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+}
+
+impl ImageAccess {
+    fn gl_enum(&self) -> GLenum {
+        match *self {
+            ImageAccess::ReadOnly => gl::READ_ONLY,
+            ImageAccess::WriteOnly => gl::WRITE_ONLY,
+            ImageAccess::ReadWrite => gl::READ_WRITE,
+        }
+    }
+}
+
+impl Program {
+    /// Connects the storage block `name` to `binding` and binds the buffer there.
+    pub fn bind_storage_buffer<B>(&self, name: &str, binding: u32, buffer: &Buffer<B>) -> Result<(), Error>
+        where B: BufferType
+    {
+        let name = name.trim_right_matches('\0');
+        let not_found = || Error::ResourceNotFound { name: name.into() };
+
+        let c_name = CString::new(name).map_err(|_| not_found())?;
+        let index = unsafe {
+            self.gl.GetProgramResourceIndex(self.id(), gl::SHADER_STORAGE_BLOCK, c_name.as_ptr())
+        };
+
+        if index == gl::INVALID_INDEX {
+            return Err(not_found());
+        }
+
+        unsafe {
+            self.gl.ShaderStorageBlockBinding(self.id(), index, binding as GLuint);
+            self.gl.BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding as GLuint, buffer.id());
+        }
+
+        Ok(())
+    }
+
+    /// Points the `image*` uniform `name` at `unit` and binds level `level`
+    /// of the texture there, interpreted as `format` (e.g. `gl::RGBA32F`).
+    pub fn bind_image(
+        &self,
+        name: &str,
+        unit: u32,
+        texture: &Texture,
+        level: i32,
+        access: ImageAccess,
+        format: GLenum,
+    ) -> Result<(), Error> {
+        let name = name.trim_right_matches('\0');
+        let not_found = || Error::ResourceNotFound { name: name.into() };
+
+        let c_name = CString::new(name).map_err(|_| not_found())?;
+        let location = unsafe {
+            self.gl.GetUniformLocation(self.id(), c_name.as_ptr())
+        };
+
+        if location == -1 {
+            return Err(not_found());
+        }
+
+        unsafe {
+            self.gl.ProgramUniform1i(self.id(), location, unit as GLint);
+            self.gl.BindImageTexture(
+                unit as GLuint,
+                texture.id(),
+                level as GLint,
+                gl::FALSE as GLboolean,
+                0,
+                access.gl_enum(),
+                format,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// `local_size_x/y/z` declared by the compute shader.
+    pub fn work_group_size(&self) -> (u32, u32, u32) {
+        let mut size: [GLint; 3] = [0; 3];
+        unsafe {
+            self.gl.GetProgramiv(self.id(), gl::COMPUTE_WORK_GROUP_SIZE, size.as_mut_ptr());
+        }
+
+        (size[0] as u32, size[1] as u32, size[2] as u32)
+    }
+
+    pub fn dispatch_compute(&self, groups_x: u32, groups_y: u32, groups_z: u32) {
+        self.bind();
+        unsafe {
+            self.gl.DispatchCompute(groups_x, groups_y, groups_z);
+        }
+    }
+
+    /// Dispatches enough work groups to cover `width * height * depth`
+    /// invocations, rounding up to whole groups.
+    pub fn dispatch_compute_for(&self, width: u32, height: u32, depth: u32) {
+        let (x, y, z) = self.work_group_size();
+        self.dispatch_compute(
+            groups_for(width, x),
+            groups_for(height, y),
+            groups_for(depth, z),
+        );
+    }
+
+    /// Reads the group counts from `buffer` at `offset` bytes,
+    /// laid out as three consecutive `uint`s.
+    pub fn dispatch_compute_indirect(&self, buffer: &DispatchIndirectBuffer, offset: usize) {
+        self.bind();
+        buffer.bind();
+        unsafe {
+            self.gl.DispatchComputeIndirect(offset as gl::types::GLintptr);
+        }
+        buffer.unbind();
+    }
+
+    /// Dispatches and issues `barrier` so the results are visible to the next consumer.
+    pub fn dispatch_compute_with_barrier(&self, groups_x: u32, groups_y: u32, groups_z: u32, barrier: Barrier) {
+        self.dispatch_compute(groups_x, groups_y, groups_z);
+        barrier.issue(&self.gl);
+    }
+}
+
+fn groups_for(invocations: u32, group_size: u32) -> u32 {
+    if group_size == 0 {
+        return 0;
+    }
+    (invocations + group_size - 1) / group_size
+}
@@ -1,6 +1,12 @@
 use gl;
 use gl::types::{GLint, GLuint, GLvoid};
 
+/// Implemented by `#[derive(VertexAttribPointers)]`, so generic code such
+/// as `Mesh<V>` can set up the attribute layout of any vertex type.
+pub trait VertexAttribPointers {
+    fn vertex_attrib_pointers(gl: &gl::Gl);
+}
+
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug)]
 #[repr(C, packed)]
@@ -36,6 +42,111 @@ impl From<(f32, f32, f32)> for f32_f32_f32 {
     }
 }
 
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Debug)]
+#[repr(C, packed)]
+pub struct f32_f32 {
+    pub d0: f32,
+    pub d1: f32,
+}
+
+impl f32_f32 {
+    pub fn new(d0: f32, d1: f32) -> f32_f32 {
+        f32_f32 {
+            d0, d1
+        }
+    }
+
+    pub unsafe fn vertex_attrib_pointer(gl: &gl::Gl, stride: usize, location: usize, offset: usize) {
+        gl.EnableVertexAttribArray(location as GLuint);
+        gl.VertexAttribPointer(
+            location as GLuint,
+            2,
+            gl::FLOAT,
+            gl::FALSE,
+            stride as GLint,
+            offset as *const GLvoid
+        );
+    }
+}
+
+impl From<(f32, f32)> for f32_f32 {
+    fn from(other: (f32, f32)) -> Self {
+        f32_f32::new(other.0, other.1)
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Debug)]
+#[repr(C, packed)]
+pub struct f32_f32_f32_f32 {
+    pub d0: f32,
+    pub d1: f32,
+    pub d2: f32,
+    pub d3: f32,
+}
+
+impl f32_f32_f32_f32 {
+    pub fn new(d0: f32, d1: f32, d2: f32, d3: f32) -> f32_f32_f32_f32 {
+        f32_f32_f32_f32 {
+            d0, d1, d2, d3
+        }
+    }
+
+    pub unsafe fn vertex_attrib_pointer(gl: &gl::Gl, stride: usize, location: usize, offset: usize) {
+        gl.EnableVertexAttribArray(location as GLuint);
+        gl.VertexAttribPointer(
+            location as GLuint,
+            4,
+            gl::FLOAT,
+            gl::FALSE,
+            stride as GLint,
+            offset as *const GLvoid
+        );
+    }
+}
+
+impl From<(f32, f32, f32, f32)> for f32_f32_f32_f32 {
+    fn from(other: (f32, f32, f32, f32)) -> Self {
+        f32_f32_f32_f32::new(other.0, other.1, other.2, other.3)
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Debug)]
+#[repr(C, packed)]
+pub struct u16_u16_u16_u16 {
+    pub d0: u16,
+    pub d1: u16,
+    pub d2: u16,
+    pub d3: u16,
+}
+
+impl u16_u16_u16_u16 {
+    pub fn new(d0: u16, d1: u16, d2: u16, d3: u16) -> u16_u16_u16_u16 {
+        u16_u16_u16_u16 {
+            d0, d1, d2, d3
+        }
+    }
+
+    pub unsafe fn vertex_attrib_pointer(gl: &gl::Gl, stride: usize, location: usize, offset: usize) {
+        gl.EnableVertexAttribArray(location as GLuint);
+        gl.VertexAttribIPointer(
+            location as GLuint,
+            4,
+            gl::UNSIGNED_SHORT,
+            stride as GLint,
+            offset as *const GLvoid
+        );
+    }
+}
+
+impl From<(u16, u16, u16, u16)> for u16_u16_u16_u16 {
+    fn from(other: (u16, u16, u16, u16)) -> Self {
+        u16_u16_u16_u16::new(other.0, other.1, other.2, other.3)
+    }
+}
+
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug)]
 #[repr(C, packed)]
@@ -0,0 +1,579 @@
+use gl;
+use gl::types::{GLbitfield, GLenum, GLint, GLsizei, GLuint};
+use image::DynamicImage;
+
+use render_gl::readback;
+use render_gl::texture::{MagFilter, Texture};
+use render_gl::viewport::Viewport;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Framebuffer size {}x{} is invalid", width, height)]
+    InvalidSize {
+        width: u32,
+        height: u32
+    },
+
+    #[fail(display = "Framebuffer is incomplete ({:#x}): {}", status, reason)]
+    Incomplete {
+        status: GLenum,
+        reason: &'static str
+    },
+}
+
+fn incomplete_reason(status: GLenum) -> &'static str {
+    match status {
+        gl::FRAMEBUFFER_UNDEFINED =>
+            "the default framebuffer does not exist",
+        gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT =>
+            "an attachment is incomplete, e.g. zero-sized or not renderable in its format",
+        gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT =>
+            "no images are attached",
+        gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER =>
+            "a draw buffer refers to an attachment point without an image",
+        gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER =>
+            "the read buffer refers to an attachment point without an image",
+        gl::FRAMEBUFFER_UNSUPPORTED =>
+            "the combination of internal formats is not supported by the driver",
+        gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE =>
+            "attachments differ in sample count or fixed sample locations",
+        gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS =>
+            "layered and non-layered attachments are mixed",
+        _ => "unknown status",
+    }
+}
+
+/// Largest sample count the driver supports for render targets.
+pub fn max_samples(gl: &gl::Gl) -> u32 {
+    let mut samples: GLint = 0;
+    unsafe {
+        gl.GetIntegerv(gl::MAX_SAMPLES, &mut samples);
+    }
+
+    samples.max(0) as u32
+}
+
+pub struct Renderbuffer {
+    gl: gl::Gl,
+    id: GLuint,
+    width: u32,
+    height: u32,
+    internal_format: GLenum,
+    samples: u32,
+}
+
+impl Renderbuffer {
+    pub fn new(gl: &gl::Gl, width: u32, height: u32, internal_format: GLenum) -> Renderbuffer {
+        Renderbuffer::new_multisample(gl, width, height, internal_format, 0)
+    }
+
+    /// `samples` of 0 creates a single-sample renderbuffer.
+    pub fn new_multisample(gl: &gl::Gl, width: u32, height: u32, internal_format: GLenum, samples: u32) -> Renderbuffer {
+        let mut id: GLuint = 0;
+        unsafe {
+            gl.GenRenderbuffers(1, &mut id);
+            gl.BindRenderbuffer(gl::RENDERBUFFER, id);
+            gl.RenderbufferStorageMultisample(
+                gl::RENDERBUFFER,
+                samples as GLsizei,
+                internal_format,
+                width as GLsizei,
+                height as GLsizei
+            );
+            gl.BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+
+        Renderbuffer {
+            gl: gl.clone(),
+            id,
+            width,
+            height,
+            internal_format,
+            samples,
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn internal_format(&self) -> GLenum {
+        self.internal_format
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteRenderbuffers(1, &mut self.id);
+        }
+    }
+}
+
+/// `TEXTURE_2D_MULTISAMPLE` render target. Shaders can read individual
+/// samples through `sampler2DMS`; for regular sampling resolve it first.
+pub struct MultisampleTexture {
+    gl: gl::Gl,
+    id: GLuint,
+    width: u32,
+    height: u32,
+    samples: u32,
+}
+
+impl MultisampleTexture {
+    pub fn new(gl: &gl::Gl, width: u32, height: u32, internal_format: GLenum, samples: u32) -> MultisampleTexture {
+        let mut id: GLuint = 0;
+        unsafe {
+            gl.GenTextures(1, &mut id);
+            gl.BindTexture(gl::TEXTURE_2D_MULTISAMPLE, id);
+            gl.TexStorage2DMultisample(
+                gl::TEXTURE_2D_MULTISAMPLE,
+                samples as GLsizei,
+                internal_format,
+                width as GLsizei,
+                height as GLsizei,
+                gl::TRUE
+            );
+            gl.BindTexture(gl::TEXTURE_2D_MULTISAMPLE, 0);
+        }
+
+        MultisampleTexture {
+            gl: gl.clone(),
+            id,
+            width,
+            height,
+            samples,
+        }
+    }
+
+    pub fn bind(&self, slot: u32) {
+        unsafe {
+            self.gl.ActiveTexture(gl::TEXTURE0 + (slot as GLenum));
+            self.gl.BindTexture(gl::TEXTURE_2D_MULTISAMPLE, self.id);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            self.gl.BindTexture(gl::TEXTURE_2D_MULTISAMPLE, 0);
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+}
+
+impl Drop for MultisampleTexture {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteTextures(1, &mut self.id);
+        }
+    }
+}
+
+/// Image attached to a framebuffer: a texture when later passes sample it,
+/// a renderbuffer when it is only rendered to or blitted from.
+pub enum Attachment {
+    Texture(Texture),
+    MultisampleTexture(MultisampleTexture),
+    Renderbuffer(Renderbuffer),
+}
+
+impl Attachment {
+    fn attach(&self, gl: &gl::Gl, point: GLenum) {
+        unsafe {
+            match *self {
+                Attachment::Texture(ref texture) =>
+                    gl.FramebufferTexture2D(gl::FRAMEBUFFER, point, gl::TEXTURE_2D, texture.id(), 0),
+                Attachment::MultisampleTexture(ref texture) =>
+                    gl.FramebufferTexture2D(gl::FRAMEBUFFER, point, gl::TEXTURE_2D_MULTISAMPLE, texture.id(), 0),
+                Attachment::Renderbuffer(ref renderbuffer) =>
+                    gl.FramebufferRenderbuffer(gl::FRAMEBUFFER, point, gl::RENDERBUFFER, renderbuffer.id()),
+            }
+        }
+    }
+
+    pub fn texture(&self) -> Option<&Texture> {
+        match *self {
+            Attachment::Texture(ref texture) => Some(texture),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AttachmentKind {
+    Texture,
+    Renderbuffer,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct AttachmentSpec {
+    kind: AttachmentKind,
+    internal_format: GLenum,
+}
+
+impl AttachmentSpec {
+    fn create(&self, gl: &gl::Gl, width: u32, height: u32, samples: u32) -> Attachment {
+        match (self.kind, samples) {
+            (AttachmentKind::Texture, 0) =>
+                Attachment::Texture(Texture::with_storage(gl, width, height, self.internal_format)),
+            (AttachmentKind::Texture, _) =>
+                Attachment::MultisampleTexture(MultisampleTexture::new(gl, width, height, self.internal_format, samples)),
+            (AttachmentKind::Renderbuffer, _) =>
+                Attachment::Renderbuffer(Renderbuffer::new_multisample(gl, width, height, self.internal_format, samples)),
+        }
+    }
+}
+
+/// Attachment point for a depth and/or stencil format.
+fn depth_stencil_point(internal_format: GLenum) -> GLenum {
+    match internal_format {
+        gl::DEPTH24_STENCIL8 | gl::DEPTH32F_STENCIL8 => gl::DEPTH_STENCIL_ATTACHMENT,
+        gl::STENCIL_INDEX8 => gl::STENCIL_ATTACHMENT,
+        _ => gl::DEPTH_ATTACHMENT,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FramebufferBuilder {
+    width: u32,
+    height: u32,
+    viewport_scale: f32,
+    samples: u32,
+    colors: Vec<AttachmentSpec>,
+    depth_stencil: Option<AttachmentSpec>,
+}
+
+impl FramebufferBuilder {
+    pub fn new(width: u32, height: u32) -> FramebufferBuilder {
+        FramebufferBuilder {
+            width,
+            height,
+            viewport_scale: 1.0,
+            samples: 0,
+            colors: Vec::new(),
+            depth_stencil: None,
+        }
+    }
+
+    /// Sized to the viewport, e.g. 0.5 for a half resolution pass.
+    pub fn for_viewport(viewport: &Viewport, scale: f32) -> FramebufferBuilder {
+        let (width, height) = scaled_size(viewport, scale);
+        let mut builder = FramebufferBuilder::new(width, height);
+        builder.viewport_scale = scale;
+        builder
+    }
+
+    /// Makes every attachment multisampled; 0 (the default) means single-sample.
+    /// Clamped to `max_samples` when the framebuffer is built.
+    pub fn samples(mut self, samples: u32) -> FramebufferBuilder {
+        self.samples = samples;
+        self
+    }
+
+    /// Adds the next color attachment (`COLOR_ATTACHMENT0`, 1, ...) as a texture.
+    pub fn color_texture(mut self, internal_format: GLenum) -> FramebufferBuilder {
+        self.colors.push(AttachmentSpec { kind: AttachmentKind::Texture, internal_format });
+        self
+    }
+
+    pub fn color_renderbuffer(mut self, internal_format: GLenum) -> FramebufferBuilder {
+        self.colors.push(AttachmentSpec { kind: AttachmentKind::Renderbuffer, internal_format });
+        self
+    }
+
+    /// Depth, stencil or combined attachment; the attachment point follows
+    /// from the format (`DEPTH24_STENCIL8` attaches to both).
+    pub fn depth_stencil(mut self, kind: AttachmentKind, internal_format: GLenum) -> FramebufferBuilder {
+        self.depth_stencil = Some(AttachmentSpec { kind, internal_format });
+        self
+    }
+
+    pub fn build(&self, gl: &gl::Gl) -> Result<Framebuffer, Error> {
+        let mut id: GLuint = 0;
+        unsafe {
+            gl.GenFramebuffers(1, &mut id);
+        }
+
+        let mut framebuffer = Framebuffer {
+            gl: gl.clone(),
+            id,
+            width: 0,
+            height: 0,
+            colors: Vec::new(),
+            depth_stencil: None,
+            spec: self.clone(),
+        };
+        framebuffer.spec.samples = self.samples.min(max_samples(gl));
+        framebuffer.resize(self.width, self.height)?;
+
+        Ok(framebuffer)
+    }
+}
+
+pub struct Framebuffer {
+    gl: gl::Gl,
+    id: GLuint,
+    width: u32,
+    height: u32,
+    colors: Vec<Attachment>,
+    depth_stencil: Option<Attachment>,
+    spec: FramebufferBuilder,
+}
+
+impl Framebuffer {
+    /// Recreates every attachment at the new size; their contents are lost.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::InvalidSize { width, height });
+        }
+
+        let gl = self.gl.clone();
+        let samples = self.spec.samples;
+        self.colors = self.spec.colors.iter()
+            .map(|spec| spec.create(&gl, width, height, samples))
+            .collect();
+        self.depth_stencil = self.spec.depth_stencil
+            .map(|spec| spec.create(&gl, width, height, samples));
+        self.width = width;
+        self.height = height;
+
+        unsafe {
+            gl.BindFramebuffer(gl::FRAMEBUFFER, self.id);
+        }
+
+        let mut draw_buffers = Vec::with_capacity(self.colors.len());
+        for (i, color) in self.colors.iter().enumerate() {
+            let point = gl::COLOR_ATTACHMENT0 + i as GLenum;
+            color.attach(&gl, point);
+            draw_buffers.push(point);
+        }
+        if let (Some(attachment), Some(spec)) = (self.depth_stencil.as_ref(), self.spec.depth_stencil) {
+            attachment.attach(&gl, depth_stencil_point(spec.internal_format));
+        }
+
+        let status = unsafe {
+            if draw_buffers.is_empty() {
+                gl.DrawBuffer(gl::NONE);
+                gl.ReadBuffer(gl::NONE);
+            } else {
+                gl.DrawBuffers(draw_buffers.len() as GLsizei, draw_buffers.as_ptr());
+                gl.ReadBuffer(gl::COLOR_ATTACHMENT0);
+            }
+
+            let status = gl.CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+            status
+        };
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(Error::Incomplete {
+                status,
+                reason: incomplete_reason(status)
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Follows `Viewport::update_size`, keeping the scale given to
+    /// `FramebufferBuilder::for_viewport`. Does nothing if the size is unchanged.
+    pub fn resize_for_viewport(&mut self, viewport: &Viewport) -> Result<(), Error> {
+        let (width, height) = scaled_size(viewport, self.spec.viewport_scale);
+        if width == self.width && height == self.height {
+            return Ok(());
+        }
+
+        self.resize(width, height)
+    }
+
+    /// Binds the framebuffer for drawing and sets the GL viewport to cover it.
+    pub fn bind(&self) {
+        unsafe {
+            self.gl.BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            self.gl.Viewport(0, 0, self.width as GLint, self.height as GLint);
+        }
+    }
+
+    /// Binds the default framebuffer; restore the window viewport with `Viewport::enable`.
+    pub fn unbind(&self) {
+        unsafe {
+            self.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    pub fn color_attachment(&self, index: usize) -> Option<&Attachment> {
+        self.colors.get(index)
+    }
+
+    pub fn color_texture(&self, index: usize) -> Option<&Texture> {
+        self.colors.get(index).and_then(Attachment::texture)
+    }
+
+    pub fn depth_stencil_attachment(&self) -> Option<&Attachment> {
+        self.depth_stencil.as_ref()
+    }
+
+    pub fn depth_texture(&self) -> Option<&Texture> {
+        self.depth_stencil.as_ref().and_then(Attachment::texture)
+    }
+
+    /// Copies the whole framebuffer into `target`, scaling if the sizes differ.
+    /// `mask` is a combination of `gl::COLOR_BUFFER_BIT`, `DEPTH_BUFFER_BIT` and
+    /// `STENCIL_BUFFER_BIT`; depth and stencil require `MagFilter::Nearest`.
+    pub fn blit_to(&self, target: &Framebuffer, mask: GLbitfield, filter: MagFilter) {
+        self.blit(target.id, 0, 0, target.width as GLint, target.height as GLint, mask, filter);
+    }
+
+    /// Copies color attachment 0 into the viewport area of the window. A
+    /// multisampled framebuffer must match the viewport size to resolve this way.
+    pub fn blit_to_default(&self, viewport: &Viewport, filter: MagFilter) {
+        self.blit(
+            0,
+            viewport.x,
+            viewport.y,
+            viewport.x + viewport.w,
+            viewport.y + viewport.h,
+            gl::COLOR_BUFFER_BIT,
+            filter,
+        );
+    }
+
+    fn blit(&self, target: GLuint, x0: GLint, y0: GLint, x1: GLint, y1: GLint, mask: GLbitfield, filter: MagFilter) {
+        unsafe {
+            self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, self.id);
+            self.gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, target);
+            self.gl.BlitFramebuffer(
+                0, 0, self.width as GLint, self.height as GLint,
+                x0, y0, x1, y1,
+                mask,
+                filter.gl_enum(),
+            );
+            self.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    /// Resolves every multisampled color attachment into the attachment with
+    /// the same index of `target`, which must be single-sample and of the same size.
+    pub fn resolve_into(&self, target: &Framebuffer) {
+        let count = self.colors.len().min(target.colors.len());
+
+        unsafe {
+            self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, self.id);
+            self.gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, target.id);
+
+            for i in 0..count {
+                let point = gl::COLOR_ATTACHMENT0 + i as GLenum;
+                self.gl.ReadBuffer(point);
+                self.gl.DrawBuffers(1, &point);
+                self.gl.BlitFramebuffer(
+                    0, 0, self.width as GLint, self.height as GLint,
+                    0, 0, target.width as GLint, target.height as GLint,
+                    gl::COLOR_BUFFER_BIT,
+                    gl::NEAREST,
+                );
+            }
+
+            // put back the draw and read buffers set up in `resize`
+            let draw_buffers = (0..target.colors.len())
+                .map(|i| gl::COLOR_ATTACHMENT0 + i as GLenum)
+                .collect::<Vec<_>>();
+            if !draw_buffers.is_empty() {
+                self.gl.DrawBuffers(draw_buffers.len() as GLsizei, draw_buffers.as_ptr());
+            }
+            if count > 0 {
+                self.gl.ReadBuffer(gl::COLOR_ATTACHMENT0);
+            }
+
+            self.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    /// Single-sample framebuffer with a color texture for every color
+    /// attachment of this one, to be used with `resolve_into`.
+    pub fn resolve_target(&self) -> Result<Framebuffer, Error> {
+        let mut builder = FramebufferBuilder::new(self.width, self.height);
+        builder.viewport_scale = self.spec.viewport_scale;
+        for spec in self.spec.colors.iter() {
+            builder = builder.color_texture(spec.internal_format);
+        }
+
+        builder.build(&self.gl)
+    }
+
+    /// 0 for a single-sample framebuffer.
+    pub fn samples(&self) -> u32 {
+        self.spec.samples
+    }
+
+    /// Reads color attachment `index` as RGBA8, top row first. Multisampled
+    /// framebuffers cannot be read directly; resolve them first.
+    pub fn to_image(&self, index: usize) -> DynamicImage {
+        unsafe {
+            self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, self.id);
+            self.gl.ReadBuffer(gl::COLOR_ATTACHMENT0 + index as GLenum);
+        }
+
+        let img = readback::read_pixels(&self.gl, 0, 0, self.width, self.height);
+
+        unsafe {
+            self.gl.ReadBuffer(gl::COLOR_ATTACHMENT0);
+            self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        }
+
+        img
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteFramebuffers(1, &mut self.id);
+        }
+    }
+}
+
+fn scaled_size(viewport: &Viewport, scale: f32) -> (u32, u32) {
+    (
+        ((viewport.w as f32 * scale) as u32).max(1),
+        ((viewport.h as f32 * scale) as u32).max(1),
+    )
+}
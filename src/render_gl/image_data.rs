@@ -0,0 +1,343 @@
+use gl;
+use gl::types::{GLenum, GLvoid};
+use image::{self, DynamicImage};
+use png;
+
+use render_gl::texture::Error;
+use resources::Resources;
+
+/// Texel storage of an `ImageData`, one element per channel.
+#[derive(Clone, Debug)]
+pub enum PixelData {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    F32(Vec<f32>),
+}
+
+/// Pixel transfer layout of client memory, as passed to `TexImage2D`
+/// and `TexSubImage2D`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PixelLayout {
+    pub format: GLenum,
+    pub pixel_type: GLenum,
+    pub bytes_per_pixel: usize,
+}
+
+/// Decoded image in the layout it is uploaded to GL: tightly packed rows,
+/// first row at the top unless `flip_vertically` has been called.
+#[derive(Clone, Debug)]
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    pub channels: u8,
+    pub bgr: bool,
+    pub data: PixelData,
+}
+
+impl ImageData {
+    /// Decodes by extension: Radiance `.hdr` to 32-bit float, 16-bit PNGs
+    /// to `u16`, everything else through `image` at 8 bits per channel.
+    pub fn from_res(res: &Resources, name: &str) -> Result<ImageData, Error> {
+        let bytes = res.load_bytes(name)
+            .map_err(|e| Error::ResourceLoad {
+                name: name.into(),
+                inner: e
+            })?;
+
+        let decoded = if name.ends_with(".hdr") {
+            decode_hdr(&bytes)
+        } else if is_png_16(&bytes) {
+            decode_png_16(&bytes)
+        } else {
+            image::load_from_memory(&bytes).map(ImageData::from_dynamic)
+        };
+
+        decoded.map_err(|e| Error::ImageDecode {
+            name: name.into(),
+            inner: e
+        })
+    }
+
+    /// Keeps the channel layout of the source instead of expanding to RGBA.
+    pub fn from_dynamic(img: DynamicImage) -> ImageData {
+        let (width, height, channels, bgr, data) = match img {
+            DynamicImage::ImageLuma8(buf) => (buf.width(), buf.height(), 1, false, buf.into_raw()),
+            DynamicImage::ImageLumaA8(buf) => (buf.width(), buf.height(), 2, false, buf.into_raw()),
+            DynamicImage::ImageRgb8(buf) => (buf.width(), buf.height(), 3, false, buf.into_raw()),
+            DynamicImage::ImageRgba8(buf) => (buf.width(), buf.height(), 4, false, buf.into_raw()),
+            DynamicImage::ImageBgr8(buf) => (buf.width(), buf.height(), 3, true, buf.into_raw()),
+            DynamicImage::ImageBgra8(buf) => (buf.width(), buf.height(), 4, true, buf.into_raw()),
+        };
+
+        ImageData {
+            width,
+            height,
+            channels,
+            bgr,
+            data: PixelData::U8(data),
+        }
+    }
+
+    /// Converts to an 8-bit `DynamicImage`. 16-bit samples keep their high
+    /// byte and floats are clamped to `[0, 1]`, since `image` has no wider formats.
+    pub fn into_dynamic(self) -> DynamicImage {
+        let (width, height, channels, bgr) = (self.width, self.height, self.channels, self.bgr);
+        let bytes = match self.data {
+            PixelData::U8(data) => data,
+            PixelData::U16(data) => data.iter().map(|v| (v >> 8) as u8).collect(),
+            PixelData::F32(data) => data.iter()
+                .map(|v| (v.max(0.0).min(1.0) * 255.0 + 0.5) as u8)
+                .collect(),
+        };
+
+        let img = match (channels, bgr) {
+            (1, _) => image::ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageLuma8),
+            (2, _) => image::ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageLumaA8),
+            (3, false) => image::ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageRgb8),
+            (3, true) => image::ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageBgr8),
+            (_, false) => image::ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageRgba8),
+            (_, true) => image::ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageBgra8),
+        };
+
+        img.expect("pixel data matches image size")
+    }
+
+    /// Reverses row order. Image files store the top row first while
+    /// GL expects the bottom row first.
+    pub fn flip_vertically(&mut self) {
+        let row_len = self.width as usize * self.channels as usize;
+        match self.data {
+            PixelData::U8(ref mut data) => flip_rows(data, row_len),
+            PixelData::U16(ref mut data) => flip_rows(data, row_len),
+            PixelData::F32(ref mut data) => flip_rows(data, row_len),
+        }
+    }
+
+    /// Copies the `width * height` rectangle starting at `x, y` (from the top-left).
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> ImageData {
+        let data = match self.data {
+            PixelData::U8(ref data) => PixelData::U8(self.crop_rows(data, x, y, width, height)),
+            PixelData::U16(ref data) => PixelData::U16(self.crop_rows(data, x, y, width, height)),
+            PixelData::F32(ref data) => PixelData::F32(self.crop_rows(data, x, y, width, height)),
+        };
+
+        ImageData {
+            width,
+            height,
+            channels: self.channels,
+            bgr: self.bgr,
+            data,
+        }
+    }
+
+    fn crop_rows<T: Copy>(&self, data: &[T], x: u32, y: u32, width: u32, height: u32) -> Vec<T> {
+        let channels = self.channels as usize;
+        let src_row_len = self.width as usize * channels;
+        let dst_row_len = width as usize * channels;
+
+        let mut cropped = Vec::with_capacity(dst_row_len * height as usize);
+        for row in y as usize..(y + height) as usize {
+            let start = row * src_row_len + x as usize * channels;
+            cropped.extend_from_slice(&data[start..start + dst_row_len]);
+        }
+
+        cropped
+    }
+
+    pub fn rotate_180(&mut self) {
+        let channels = self.channels as usize;
+        match self.data {
+            PixelData::U8(ref mut data) => reverse_pixels(data, channels),
+            PixelData::U16(ref mut data) => reverse_pixels(data, channels),
+            PixelData::F32(ref mut data) => reverse_pixels(data, channels),
+        }
+    }
+
+    /// True when both images can be uploaded as layers of one texture.
+    pub fn same_layout(&self, other: &ImageData) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.channels == other.channels
+            && self.bgr == other.bgr
+            && self.gl_type() == other.gl_type()
+    }
+
+    /// Pixel transfer format, e.g. `gl::RGB` or `gl::BGRA`.
+    pub fn gl_format(&self) -> GLenum {
+        match (self.channels, self.bgr) {
+            (1, _) => gl::RED,
+            (2, _) => gl::RG,
+            (3, false) => gl::RGB,
+            (3, true) => gl::BGR,
+            (_, false) => gl::RGBA,
+            (_, true) => gl::BGRA,
+        }
+    }
+
+    /// Pixel transfer type, e.g. `gl::UNSIGNED_BYTE`.
+    pub fn gl_type(&self) -> GLenum {
+        match self.data {
+            PixelData::U8(_) => gl::UNSIGNED_BYTE,
+            PixelData::U16(_) => gl::UNSIGNED_SHORT,
+            PixelData::F32(_) => gl::FLOAT,
+        }
+    }
+
+    /// Sized internal format matching the source precision. sRGB is only
+    /// available for 8-bit RGB(A); float sources are stored as half floats.
+    pub fn internal_format(&self, srgb: bool) -> GLenum {
+        match self.data {
+            PixelData::U8(_) => match (self.channels, srgb) {
+                (1, _) => gl::R8,
+                (2, _) => gl::RG8,
+                (3, false) => gl::RGB8,
+                (3, true) => gl::SRGB8,
+                (_, false) => gl::RGBA8,
+                (_, true) => gl::SRGB8_ALPHA8,
+            },
+            PixelData::U16(_) => match self.channels {
+                1 => gl::R16,
+                2 => gl::RG16,
+                3 => gl::RGB16,
+                _ => gl::RGBA16,
+            },
+            PixelData::F32(_) => match self.channels {
+                1 => gl::R16F,
+                2 => gl::RG16F,
+                3 => gl::RGB16F,
+                _ => gl::RGBA16F,
+            },
+        }
+    }
+
+    /// Swizzle that makes gray and gray-alpha images sample as gray
+    /// instead of red, or `None` when the channels map directly.
+    pub fn swizzle(&self) -> Option<[GLenum; 4]> {
+        match self.channels {
+            1 => Some([gl::RED, gl::RED, gl::RED, gl::ONE]),
+            2 => Some([gl::RED, gl::RED, gl::RED, gl::GREEN]),
+            _ => None,
+        }
+    }
+
+    pub fn layout(&self) -> PixelLayout {
+        let bytes_per_channel = match self.data {
+            PixelData::U8(_) => 1,
+            PixelData::U16(_) => 2,
+            PixelData::F32(_) => 4,
+        };
+
+        PixelLayout {
+            format: self.gl_format(),
+            pixel_type: self.gl_type(),
+            bytes_per_pixel: self.channels as usize * bytes_per_channel,
+        }
+    }
+
+    pub fn as_ptr(&self) -> *const GLvoid {
+        match self.data {
+            PixelData::U8(ref data) => data.as_ptr() as *const GLvoid,
+            PixelData::U16(ref data) => data.as_ptr() as *const GLvoid,
+            PixelData::F32(ref data) => data.as_ptr() as *const GLvoid,
+        }
+    }
+}
+
+fn flip_rows<T>(data: &mut [T], row_len: usize) {
+    if row_len == 0 {
+        return;
+    }
+
+    let rows = data.len() / row_len;
+    for y in 0..rows / 2 {
+        let (head, tail) = data.split_at_mut((rows - 1 - y) * row_len);
+        head[y * row_len..(y + 1) * row_len].swap_with_slice(&mut tail[..row_len]);
+    }
+}
+
+fn reverse_pixels<T>(data: &mut [T], channels: usize) {
+    data.reverse();
+    // reversing the whole buffer also reverses channel order within each pixel
+    for pixel in data.chunks_mut(channels) {
+        pixel.reverse();
+    }
+}
+
+fn decode_hdr(bytes: &[u8]) -> Result<ImageData, image::ImageError> {
+    let decoder = image::hdr::HDRDecoder::new(bytes)?;
+    let meta = decoder.metadata();
+    let pixels = decoder.read_image_hdr()?;
+
+    let mut data = Vec::with_capacity(pixels.len() * 3);
+    for pixel in pixels {
+        data.extend_from_slice(&pixel.data);
+    }
+
+    Ok(ImageData {
+        width: meta.width,
+        height: meta.height,
+        channels: 3,
+        bgr: false,
+        data: PixelData::F32(data),
+    })
+}
+
+// PNG signature (8) + IHDR length (4) + "IHDR" (4) + width (4) + height (4)
+const PNG_BIT_DEPTH_OFFSET: usize = 24;
+
+fn is_png_16(bytes: &[u8]) -> bool {
+    const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+    bytes.len() > PNG_BIT_DEPTH_OFFSET
+        && bytes[..8] == SIGNATURE
+        && bytes[PNG_BIT_DEPTH_OFFSET] == 16
+}
+
+fn decode_png_16(bytes: &[u8]) -> Result<ImageData, image::ImageError> {
+    use png::HasParameters;
+
+    // the default transformations also include SCALE_16 and STRIP_16,
+    // which would hand back 8-bit samples
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set(png::Transformations::EXPAND);
+    let (info, mut reader) = decoder.read_info().map_err(image::ImageError::from)?;
+
+    // png 0.12 reports expanded output as 8-bit even when it keeps 16 bits,
+    // so check the depth in the header
+    if reader.info().bit_depth != png::BitDepth::Sixteen {
+        return Err(image::ImageError::FormatError("expected a 16-bit PNG".into()));
+    }
+
+    let mut buffer = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buffer).map_err(image::ImageError::from)?;
+
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::RGB => 3,
+        png::ColorType::RGBA => 4,
+        png::ColorType::Indexed => return Err(image::ImageError::FormatError(
+            "16-bit indexed PNG".into()
+        )),
+    };
+
+    let samples = info.width as usize * info.height as usize * channels as usize;
+    if buffer.len() != samples * 2 {
+        return Err(image::ImageError::FormatError(
+            format!("16-bit PNG decoded to {} bytes, expected {}", buffer.len(), samples * 2)
+        ));
+    }
+
+    // PNG stores samples big-endian
+    let data = buffer.chunks(2)
+        .map(|pair| (pair[0] as u16) << 8 | pair[1] as u16)
+        .collect();
+
+    Ok(ImageData {
+        width: info.width,
+        height: info.height,
+        channels,
+        bgr: false,
+        data: PixelData::U16(data),
+    })
+}
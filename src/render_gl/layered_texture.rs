@@ -0,0 +1,312 @@
+use gl;
+use gl::types::{GLenum, GLint, GLuint};
+
+use render_gl::image_data::ImageData;
+use render_gl::texture::{self, Error, TextureBuilder};
+use resources::Resources;
+
+pub trait TextureTarget {
+    const TEXTURE_TARGET: GLenum;
+}
+
+pub struct Texture2DArrayTarget;
+impl TextureTarget for Texture2DArrayTarget {
+    const TEXTURE_TARGET: GLenum = gl::TEXTURE_2D_ARRAY;
+}
+
+pub struct Texture3DTarget;
+impl TextureTarget for Texture3DTarget {
+    const TEXTURE_TARGET: GLenum = gl::TEXTURE_3D;
+}
+
+pub struct TextureCubeTarget;
+impl TextureTarget for TextureCubeTarget {
+    const TEXTURE_TARGET: GLenum = gl::TEXTURE_CUBE_MAP;
+}
+
+pub type Texture2DArray = LayeredTexture<Texture2DArrayTarget>;
+pub type Texture3D = LayeredTexture<Texture3DTarget>;
+pub type TextureCube = LayeredTexture<TextureCubeTarget>;
+
+/// Texture made of several equally sized 2D images: array layers,
+/// depth slices of a volume, or the six faces of a cube map.
+pub struct LayeredTexture<T> where T: TextureTarget {
+    gl: gl::Gl,
+    id: GLuint,
+    width: u32,
+    height: u32,
+    depth: u32,
+    _marker: ::std::marker::PhantomData<T>,
+}
+
+impl<T> LayeredTexture<T> where T: TextureTarget {
+    /// Takes ownership of an already filled texture object of target `T`.
+    pub(crate) fn from_raw(gl: &gl::Gl, id: GLuint, width: u32, height: u32, depth: u32) -> LayeredTexture<T> {
+        LayeredTexture {
+            gl: gl.clone(),
+            id,
+            width,
+            height,
+            depth,
+            _marker: ::std::marker::PhantomData,
+        }
+    }
+
+    pub fn bind(&self, slot: u32) {
+        unsafe {
+            self.gl.ActiveTexture(gl::TEXTURE0 + (slot as GLenum));
+            self.gl.BindTexture(T::TEXTURE_TARGET, self.id);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            self.gl.BindTexture(T::TEXTURE_TARGET, 0);
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Number of array layers, volume slices, or 6 for a cube map.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+}
+
+impl<T> Drop for LayeredTexture<T> where T: TextureTarget {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteTextures(1, &mut self.id);
+        }
+    }
+}
+
+impl Texture2DArray {
+    pub fn from_res(gl: &gl::Gl, res: &Resources, names: &[&str]) -> Result<Texture2DArray, Error> {
+        TextureBuilder::new().array_from_res(gl, res, names)
+    }
+}
+
+impl Texture3D {
+    pub fn from_res(gl: &gl::Gl, res: &Resources, names: &[&str]) -> Result<Texture3D, Error> {
+        TextureBuilder::new().volume_from_res(gl, res, names)
+    }
+}
+
+impl TextureCube {
+    pub fn from_res(gl: &gl::Gl, res: &Resources, faces: &[&str; 6]) -> Result<TextureCube, Error> {
+        TextureBuilder::new().cube_from_res(gl, res, faces)
+    }
+}
+
+impl TextureBuilder {
+    /// One array layer per image, in the given order.
+    pub fn array_from_res(&self, gl: &gl::Gl, res: &Resources, names: &[&str]) -> Result<Texture2DArray, Error> {
+        let layers = self.load_layers(res, names)?;
+        Ok(self.upload_layers(gl, &layers))
+    }
+
+    /// One array layer per file in the folder, sorted by file name.
+    pub fn array_from_res_dir(&self, gl: &gl::Gl, res: &Resources, dir: &str) -> Result<Texture2DArray, Error> {
+        let names = list_dir(res, dir)?;
+        let names = names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+        self.array_from_res(gl, res, &names)
+    }
+
+    /// One depth slice per image, first image at `r = 0`.
+    pub fn volume_from_res(&self, gl: &gl::Gl, res: &Resources, names: &[&str]) -> Result<Texture3D, Error> {
+        let slices = self.load_layers(res, names)?;
+        Ok(self.upload_layers(gl, &slices))
+    }
+
+    /// One depth slice per file in the folder, sorted by file name.
+    pub fn volume_from_res_dir(&self, gl: &gl::Gl, res: &Resources, dir: &str) -> Result<Texture3D, Error> {
+        let names = list_dir(res, dir)?;
+        let names = names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+        self.volume_from_res(gl, res, &names)
+    }
+
+    /// Faces in GL order: +X, -X, +Y, -Y, +Z, -Z. Cube maps are addressed
+    /// with the top row first, so `flip_vertically` is not applied.
+    pub fn cube_from_res(&self, gl: &gl::Gl, res: &Resources, faces: &[&str; 6]) -> Result<TextureCube, Error> {
+        let mut images = Vec::with_capacity(6);
+        for name in faces.iter() {
+            images.push(ImageData::from_res(res, name)?);
+        }
+        check_layers(&images, &faces[..])?;
+
+        Ok(self.upload_cube(gl, &images))
+    }
+
+    /// Cuts the faces out of one image laid out as a horizontal (4x3)
+    /// or vertical (3x4) cross:
+    ///
+    /// ```text
+    ///     +Y              +Y
+    /// -X  +Z  +X  -Z  -X  +Z  +X
+    ///     -Y              -Y
+    ///                     -Z (upside down)
+    /// ```
+    pub fn cube_from_res_cross(&self, gl: &gl::Gl, res: &Resources, name: &str) -> Result<TextureCube, Error> {
+        let cross = ImageData::from_res(res, name)?;
+
+        let horizontal = cross.width % 4 == 0 && cross.height % 3 == 0
+            && cross.width / 4 == cross.height / 3;
+        let vertical = cross.width % 3 == 0 && cross.height % 4 == 0
+            && cross.width / 3 == cross.height / 4;
+
+        let images = if horizontal {
+            let size = cross.width / 4;
+            let cell = |col: u32, row: u32| cross.crop(col * size, row * size, size, size);
+            vec![cell(2, 1), cell(0, 1), cell(1, 0), cell(1, 2), cell(1, 1), cell(3, 1)]
+        } else if vertical {
+            let size = cross.width / 3;
+            let cell = |col: u32, row: u32| cross.crop(col * size, row * size, size, size);
+            let mut negative_z = cell(1, 3);
+            negative_z.rotate_180();
+            vec![cell(2, 1), cell(0, 1), cell(1, 0), cell(1, 2), cell(1, 1), negative_z]
+        } else {
+            return Err(Error::InvalidCrossLayout {
+                name: name.into(),
+                width: cross.width,
+                height: cross.height
+            });
+        };
+
+        Ok(self.upload_cube(gl, &images))
+    }
+
+    fn load_layers(&self, res: &Resources, names: &[&str]) -> Result<Vec<ImageData>, Error> {
+        let mut layers = Vec::with_capacity(names.len());
+        for name in names {
+            layers.push(self.load_image_data(res, name)?);
+        }
+        check_layers(&layers, names)?;
+
+        Ok(layers)
+    }
+
+    fn upload_layers<T>(&self, gl: &gl::Gl, layers: &[ImageData]) -> LayeredTexture<T>
+        where T: TextureTarget
+    {
+        let first = &layers[0];
+        let texture = self.create::<T>(gl, first, layers.len() as u32);
+
+        unsafe {
+            gl.TexImage3D(
+                T::TEXTURE_TARGET,
+                0,
+                self.internal_format(first) as GLint,
+                first.width as GLint,
+                first.height as GLint,
+                layers.len() as GLint,
+                0,
+                first.gl_format(),
+                first.gl_type(),
+                ::std::ptr::null()
+            );
+
+            for (i, layer) in layers.iter().enumerate() {
+                gl.TexSubImage3D(
+                    T::TEXTURE_TARGET,
+                    0,
+                    0,
+                    0,
+                    i as GLint,
+                    layer.width as GLint,
+                    layer.height as GLint,
+                    1,
+                    layer.gl_format(),
+                    layer.gl_type(),
+                    layer.as_ptr()
+                );
+            }
+        }
+
+        self.finish(gl, texture)
+    }
+
+    fn upload_cube(&self, gl: &gl::Gl, faces: &[ImageData]) -> TextureCube {
+        let texture = self.create::<TextureCubeTarget>(gl, &faces[0], 6);
+
+        for (i, face) in faces.iter().enumerate() {
+            unsafe {
+                gl.TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + i as GLenum,
+                    0,
+                    self.internal_format(face) as GLint,
+                    face.width as GLint,
+                    face.height as GLint,
+                    0,
+                    face.gl_format(),
+                    face.gl_type(),
+                    face.as_ptr()
+                );
+            }
+        }
+
+        self.finish(gl, texture)
+    }
+
+    fn create<T>(&self, gl: &gl::Gl, first: &ImageData, depth: u32) -> LayeredTexture<T>
+        where T: TextureTarget
+    {
+        let mut texture_id: GLuint = 0;
+        unsafe {
+            gl.GenTextures(1, &mut texture_id);
+            gl.BindTexture(T::TEXTURE_TARGET, texture_id);
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        }
+
+        self.apply_parameters(gl, T::TEXTURE_TARGET);
+        texture::apply_swizzle(gl, T::TEXTURE_TARGET, first);
+
+        LayeredTexture::from_raw(gl, texture_id, first.width, first.height, depth)
+    }
+
+    fn finish<T>(&self, gl: &gl::Gl, texture: LayeredTexture<T>) -> LayeredTexture<T>
+        where T: TextureTarget
+    {
+        self.generate_mipmaps(gl, T::TEXTURE_TARGET);
+
+        unsafe {
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl.BindTexture(T::TEXTURE_TARGET, 0);
+        }
+
+        texture
+    }
+}
+
+fn list_dir(res: &Resources, dir: &str) -> Result<Vec<String>, Error> {
+    res.list_dir(dir)
+        .map_err(|e| Error::ResourceLoad {
+            name: dir.into(),
+            inner: e
+        })
+}
+
+fn check_layers<S: AsRef<str>>(layers: &[ImageData], names: &[S]) -> Result<(), Error> {
+    let first = match layers.first() {
+        Some(first) => first,
+        None => return Err(Error::NoImages),
+    };
+
+    for (layer, name) in layers.iter().zip(names).skip(1) {
+        if !first.same_layout(layer) {
+            return Err(Error::ImageMismatch { name: name.as_ref().into() });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use gl;
+use gl::types::{GLenum, GLsizei, GLvoid};
+
+use render_gl::buffer::{ElementArray, VertexArray, VertexBuffer};
+use render_gl::data::VertexAttribPointers;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Primitive {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl Primitive {
+    fn gl_enum(&self) -> GLenum {
+        match *self {
+            Primitive::Points => gl::POINTS,
+            Primitive::Lines => gl::LINES,
+            Primitive::LineStrip => gl::LINE_STRIP,
+            Primitive::LineLoop => gl::LINE_LOOP,
+            Primitive::Triangles => gl::TRIANGLES,
+            Primitive::TriangleStrip => gl::TRIANGLE_STRIP,
+            Primitive::TriangleFan => gl::TRIANGLE_FAN,
+        }
+    }
+}
+
+pub trait IndexType {
+    const INDEX_TYPE: GLenum;
+}
+
+impl IndexType for u8 {
+    const INDEX_TYPE: GLenum = gl::UNSIGNED_BYTE;
+}
+
+impl IndexType for u16 {
+    const INDEX_TYPE: GLenum = gl::UNSIGNED_SHORT;
+}
+
+impl IndexType for u32 {
+    const INDEX_TYPE: GLenum = gl::UNSIGNED_INT;
+}
+
+struct Indices {
+    _ibo: ElementArray,
+    index_type: GLenum,
+    index_size: usize,
+}
+
+/// Vertex buffer, optional index buffer and the vertex array tying them to
+/// the attribute layout of `V`.
+///
+/// `draw` renders the current draw range, which starts out covering every
+/// index (or every vertex without indices); the caller binds the program.
+pub struct Mesh<V> where V: VertexAttribPointers {
+    gl: gl::Gl,
+    vao: VertexArray,
+    _vbo: VertexBuffer,
+    indices: Option<Indices>,
+    primitive: Primitive,
+    count: usize,
+    first: usize,
+    draw_count: usize,
+    _marker: ::std::marker::PhantomData<V>,
+}
+
+impl<V> Mesh<V> where V: VertexAttribPointers {
+    pub fn new(gl: &gl::Gl, vertices: &[V], primitive: Primitive) -> Mesh<V> {
+        let (vao, vbo) = Mesh::<V>::upload_vertices(gl, vertices);
+        vao.unbind();
+        vbo.unbind();
+
+        Mesh::from_parts(gl, vao, vbo, None, primitive, vertices.len())
+    }
+
+    pub fn indexed<I>(gl: &gl::Gl, vertices: &[V], indices: &[I], primitive: Primitive) -> Mesh<V>
+        where I: IndexType
+    {
+        let (vao, vbo) = Mesh::<V>::upload_vertices(gl, vertices);
+
+        let ibo = ElementArray::new(gl);
+        ibo.bind();
+        ibo.static_draw_data(indices);
+
+        vao.unbind();
+        vbo.unbind();
+        ibo.unbind();
+
+        let indices_info = Indices {
+            _ibo: ibo,
+            index_type: I::INDEX_TYPE,
+            index_size: ::std::mem::size_of::<I>(),
+        };
+        Mesh::from_parts(gl, vao, vbo, Some(indices_info), primitive, indices.len())
+    }
+
+    fn upload_vertices(gl: &gl::Gl, vertices: &[V]) -> (VertexArray, VertexBuffer) {
+        let vao = VertexArray::new(gl);
+        vao.bind();
+
+        let vbo = VertexBuffer::new(gl);
+        vbo.bind();
+        vbo.static_draw_data(vertices);
+        V::vertex_attrib_pointers(gl);
+
+        (vao, vbo)
+    }
+
+    fn from_parts(
+        gl: &gl::Gl,
+        vao: VertexArray,
+        vbo: VertexBuffer,
+        indices: Option<Indices>,
+        primitive: Primitive,
+        count: usize
+    ) -> Mesh<V> {
+        Mesh {
+            gl: gl.clone(),
+            vao,
+            _vbo: vbo,
+            indices,
+            primitive,
+            count,
+            first: 0,
+            draw_count: count,
+            _marker: ::std::marker::PhantomData,
+        }
+    }
+
+    pub fn primitive(&self) -> Primitive {
+        self.primitive
+    }
+
+    pub fn set_primitive(&mut self, primitive: Primitive) {
+        self.primitive = primitive;
+    }
+
+    /// Number of indices, or of vertices for a mesh without indices.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        self.indices.is_some()
+    }
+
+    /// Draws only `count` indices (or vertices) starting at `first`;
+    /// clamped to the mesh.
+    pub fn set_draw_range(&mut self, first: usize, count: usize) {
+        self.first = first.min(self.count);
+        self.draw_count = count.min(self.count - self.first);
+    }
+
+    pub fn reset_draw_range(&mut self) {
+        self.first = 0;
+        self.draw_count = self.count;
+    }
+
+    pub fn draw(&self) {
+        self.vao.bind();
+
+        unsafe {
+            match self.indices {
+                Some(ref indices) => self.gl.DrawElements(
+                    self.primitive.gl_enum(),
+                    self.draw_count as GLsizei,
+                    indices.index_type,
+                    (self.first * indices.index_size) as *const GLvoid
+                ),
+                None => self.gl.DrawArrays(
+                    self.primitive.gl_enum(),
+                    self.first as gl::types::GLint,
+                    self.draw_count as GLsizei
+                ),
+            }
+        }
+
+        self.vao.unbind();
+    }
+}
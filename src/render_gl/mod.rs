@@ -1,11 +1,39 @@
 pub mod data;
 pub mod buffer;
+mod color;
 mod color_buffer;
 mod viewport;
 mod texture;
+mod image_data;
+mod layered_texture;
+mod compressed;
+mod atlas;
+mod texture_stream;
+mod readback;
+mod framebuffer;
+mod sampler;
 mod shader;
+mod program_pipeline;
+mod compute;
+mod post_process;
+mod render_state;
+mod mesh;
 
+pub use self::color::{Color, srgb_to_linear, linear_to_srgb, Error as ColorError};
 pub use self::color_buffer::ColorBuffer;
 pub use self::shader::{Shader, Program, Error};
-pub use self::texture::Texture;
-pub use self::viewport::Viewport;
\ No newline at end of file
+pub use self::program_pipeline::ProgramPipeline;
+pub use self::compute::{Barrier, ImageAccess};
+pub use self::texture::{Texture, TextureBuilder, MinFilter, MagFilter, Wrap, Error as TextureError};
+pub use self::layered_texture::{LayeredTexture, TextureTarget, Texture2DArray, Texture3D, TextureCube};
+pub use self::atlas::{TextureAtlas, AtlasBuilder, AtlasRegion};
+pub use self::compressed::{CompressedFormat, CompressedImage};
+pub use self::image_data::{ImageData, PixelData, PixelLayout};
+pub use self::texture_stream::TextureStream;
+pub use self::readback::read_default_framebuffer;
+pub use self::framebuffer::{Framebuffer, FramebufferBuilder, Renderbuffer, MultisampleTexture, Attachment, max_samples, AttachmentKind, Error as FramebufferError};
+pub use self::sampler::{Sampler, CompareFunc};
+pub use self::post_process::{PostProcess, PostPass};
+pub use self::render_state::{RenderState, StateTracker, DepthState, BlendState, BlendEquation, BlendFactor, CullFace, FrontFace, StencilState, StencilFace, StencilOp, ScissorRect, PolygonMode};
+pub use self::mesh::{Mesh, Primitive, IndexType};
+pub use self::viewport::{Viewport, FitMode};
\ No newline at end of file
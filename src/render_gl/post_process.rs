@@ -0,0 +1,337 @@
+use gl;
+use gl::types::GLint;
+use std::ffi::{CStr, CString};
+
+use render_gl::buffer::VertexArray;
+use render_gl::framebuffer::{AttachmentKind, Error as FramebufferError, Framebuffer, FramebufferBuilder};
+use render_gl::shader::{Error, Program, Shader};
+use render_gl::texture::Texture;
+use render_gl::viewport::Viewport;
+use resources::Resources;
+
+/// Texture unit of the previous pass output, `u_texture` in the shader.
+const INPUT_SLOT: u32 = 0;
+/// Texture unit of the untouched scene color, `u_scene` in the shader.
+const SCENE_SLOT: u32 = 1;
+/// First texture unit of the textures added with `PostPass::with_texture`.
+const EXTRA_SLOT: u32 = 2;
+
+/// Vertex shader shared by every pass.
+const FULLSCREEN_VERTEX_SHADER: &str = "shaders/post/fullscreen.vert.shader";
+
+/// Pass-through used when no pass is enabled. A multisampled window
+/// framebuffer cannot be the target of `BlitFramebuffer`, so the scene is
+/// drawn instead of blitted.
+const COPY_FRAGMENT_SOURCE: &str = "#version 330 core
+
+in vec2 TexCoord;
+
+out vec4 Color;
+
+uniform sampler2D u_texture;
+
+void main()
+{
+    Color = texture(u_texture, TexCoord);
+}
+";
+
+/// One full-screen fragment pass.
+///
+/// A pass named `shaders/post/vignette` loads `vignette.frag.shader` and
+/// runs it with the shared `shaders/post/fullscreen.vert.shader`, which
+/// draws one triangle without attributes and passes `TexCoord` on. The
+/// fragment shader may declare any of:
+///
+/// - `sampler2D u_texture`: output of the previous pass, or the scene for the first one
+/// - `sampler2D u_scene`: the scene as rendered, e.g. for bloom composition
+/// - `vec2 u_texel_size`: `1 / size` of the target
+pub struct PostPass {
+    name: String,
+    program: Program,
+    enabled: bool,
+    textures: Vec<(GLint, Texture)>,
+    input_location: GLint,
+    scene_location: GLint,
+    texel_size_location: GLint,
+}
+
+impl PostPass {
+    pub fn from_res(gl: &gl::Gl, res: &Resources, name: &str) -> Result<PostPass, Error> {
+        let shaders = [
+            Shader::from_res(gl, res, FULLSCREEN_VERTEX_SHADER)?,
+            Shader::from_res(gl, res, &format!("{}.frag.shader", name))?,
+        ];
+
+        let program = Program::from_shaders(gl, &shaders)
+            .map_err(|message| Error::LinkError {
+                name: name.into(),
+                message
+            })?;
+
+        Ok(PostPass::new(name, program))
+    }
+
+    fn new(name: &str, program: Program) -> PostPass {
+        PostPass {
+            name: name.into(),
+            input_location: optional_location(&program, "u_texture\0"),
+            scene_location: optional_location(&program, "u_scene\0"),
+            texel_size_location: optional_location(&program, "u_texel_size\0"),
+            program,
+            enabled: true,
+            textures: Vec::new(),
+        }
+    }
+
+    /// The built-in pass-through, compiled from sources in the binary so
+    /// `PostProcess::new` needs no resources.
+    fn copy(gl: &gl::Gl) -> PostPass {
+        let vertex = CString::new(include_str!("../../assets/shaders/post/fullscreen.vert.shader"))
+            .expect("shader source has no nul bytes");
+        let fragment = CString::new(COPY_FRAGMENT_SOURCE)
+            .expect("shader source has no nul bytes");
+
+        let shaders = [
+            Shader::from_vert_source(gl, &vertex).expect("built-in vertex shader compiles"),
+            Shader::from_frag_source(gl, &fragment).expect("built-in copy shader compiles"),
+        ];
+        let program = Program::from_shaders(gl, &shaders)
+            .expect("built-in copy program links");
+
+        PostPass::new("copy", program)
+    }
+
+    /// Binds `texture` to the sampler `uniform` (nul-terminated) on every run,
+    /// e.g. the lookup table of a color grading pass.
+    pub fn with_texture(mut self, uniform: &str, texture: Texture) -> PostPass {
+        let location = self.program.uniform_location(uniform);
+        self.textures.push((location, texture));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// For pass specific uniforms such as a bloom threshold or vignette strength.
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    fn run(&self, gl: &gl::Gl, input: &Texture, scene: &Texture, width: u32, height: u32) {
+        self.program.bind();
+
+        input.bind(INPUT_SLOT);
+        scene.bind(SCENE_SLOT);
+        for (i, &(_, ref texture)) in self.textures.iter().enumerate() {
+            texture.bind(EXTRA_SLOT + i as u32);
+        }
+
+        let id = self.program.id();
+        unsafe {
+            gl.ProgramUniform1i(id, self.input_location, INPUT_SLOT as GLint);
+            gl.ProgramUniform1i(id, self.scene_location, SCENE_SLOT as GLint);
+            gl.ProgramUniform2f(id, self.texel_size_location, 1.0 / width as f32, 1.0 / height as f32);
+            for (i, &(location, _)) in self.textures.iter().enumerate() {
+                gl.ProgramUniform1i(id, location, (EXTRA_SLOT + i as u32) as GLint);
+            }
+
+            gl.DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+    }
+}
+
+/// Looks up a uniform the shader may leave out; -1 makes `ProgramUniform*` a no-op.
+fn optional_location(program: &Program, name: &str) -> GLint {
+    debug_assert!(name.ends_with('\0'), "uniform name {:?} must be nul-terminated", name);
+    unsafe {
+        let c_name = CStr::from_bytes_with_nul_unchecked(name.as_bytes());
+        program.gl.GetUniformLocation(program.id(), c_name.as_ptr())
+    }
+}
+
+/// Renders the scene into an off-screen HDR target and runs the enabled
+/// passes over it in order, the last one straight into the window.
+///
+/// Intermediate results alternate between two framebuffers, so any number
+/// of passes needs only two extra color textures.
+///
+/// ```ignore
+/// post.begin();
+/// // draw the scene
+/// post.finish(&viewport);
+/// ```
+pub struct PostProcess {
+    gl: gl::Gl,
+    scene: Framebuffer,
+    resolved: Option<Framebuffer>,
+    targets: [Framebuffer; 2],
+    passes: Vec<PostPass>,
+    copy: PostPass,
+    vao: VertexArray,
+}
+
+impl PostProcess {
+    /// With `samples` above 0 the scene is rendered with MSAA and resolved
+    /// before the first pass.
+    pub fn new(gl: &gl::Gl, viewport: &Viewport, samples: u32) -> Result<PostProcess, FramebufferError> {
+        let scene = FramebufferBuilder::for_viewport(viewport, 1.0)
+            .samples(samples)
+            .color_texture(gl::RGBA16F)
+            .depth_stencil(AttachmentKind::Renderbuffer, gl::DEPTH24_STENCIL8)
+            .build(gl)?;
+
+        let resolved = if scene.samples() > 0 {
+            Some(scene.resolve_target()?)
+        } else {
+            None
+        };
+
+        let target = FramebufferBuilder::for_viewport(viewport, 1.0)
+            .color_texture(gl::RGBA16F);
+
+        Ok(PostProcess {
+            gl: gl.clone(),
+            scene,
+            resolved,
+            targets: [target.build(gl)?, target.build(gl)?],
+            passes: Vec::new(),
+            copy: PostPass::copy(gl),
+            vao: VertexArray::new(gl),
+        })
+    }
+
+    pub fn add_pass(&mut self, pass: PostPass) {
+        self.passes.push(pass);
+    }
+
+    /// Index is clamped to the number of passes.
+    pub fn insert_pass(&mut self, index: usize, pass: PostPass) {
+        let index = index.min(self.passes.len());
+        self.passes.insert(index, pass);
+    }
+
+    pub fn remove_pass(&mut self, name: &str) -> Option<PostPass> {
+        let index = self.position(name)?;
+        Some(self.passes.remove(index))
+    }
+
+    /// Moves the pass to `index` in the run order. Returns false if there is no such pass.
+    pub fn move_pass(&mut self, name: &str, index: usize) -> bool {
+        match self.remove_pass(name) {
+            Some(pass) => {
+                self.insert_pass(index, pass);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns false if there is no such pass.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.pass_mut(name) {
+            Some(pass) => {
+                pass.set_enabled(enabled);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn pass(&self, name: &str) -> Option<&PostPass> {
+        self.passes.iter().find(|pass| pass.name == name)
+    }
+
+    pub fn pass_mut(&mut self, name: &str) -> Option<&mut PostPass> {
+        self.passes.iter_mut().find(|pass| pass.name == name)
+    }
+
+    /// Passes in run order, enabled or not.
+    pub fn passes(&self) -> &[PostPass] {
+        &self.passes
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.passes.iter().position(|pass| pass.name == name)
+    }
+
+    /// Call after `Viewport::update_size`.
+    pub fn resize(&mut self, viewport: &Viewport) -> Result<(), FramebufferError> {
+        self.scene.resize_for_viewport(viewport)?;
+        if let Some(ref mut resolved) = self.resolved {
+            resolved.resize_for_viewport(viewport)?;
+        }
+        for target in self.targets.iter_mut() {
+            target.resize_for_viewport(viewport)?;
+        }
+
+        Ok(())
+    }
+
+    /// Scene framebuffer; drawing between `begin` and `finish` goes here.
+    pub fn scene(&self) -> &Framebuffer {
+        &self.scene
+    }
+
+    pub fn begin(&self) {
+        self.scene.bind();
+    }
+
+    /// Runs the enabled passes and leaves the window framebuffer bound with
+    /// `viewport` enabled. Without enabled passes the scene is copied as is.
+    pub fn finish(&self, viewport: &Viewport) {
+        let scene = match self.resolved {
+            Some(ref resolved) => {
+                self.scene.resolve_into(resolved);
+                resolved
+            },
+            None => &self.scene,
+        };
+
+        let mut enabled = self.passes.iter()
+            .filter(|pass| pass.enabled)
+            .collect::<Vec<_>>();
+
+        if enabled.is_empty() {
+            enabled.push(&self.copy);
+        }
+
+        let scene_color = scene.color_texture(0)
+            .expect("post process targets have a color texture");
+
+        self.vao.bind();
+        let mut input = scene_color;
+        for (i, pass) in enabled.iter().enumerate() {
+            let (width, height) = if i + 1 == enabled.len() {
+                self.scene.unbind();
+                viewport.enable(&self.gl);
+                (viewport.w as u32, viewport.h as u32)
+            } else {
+                let target = &self.targets[i % 2];
+                target.bind();
+                (target.width(), target.height())
+            };
+
+            pass.run(&self.gl, input, scene_color, width, height);
+
+            if i + 1 < enabled.len() {
+                input = self.targets[i % 2].color_texture(0)
+                    .expect("post process targets have a color texture");
+            }
+        }
+        self.vao.unbind();
+
+        unsafe {
+            self.gl.UseProgram(0);
+        }
+    }
+}
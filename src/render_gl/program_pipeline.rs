@@ -0,0 +1,107 @@
+use gl;
+use std::ffi::CString;
+
+use render_gl::shader::{Program, Error, whitespace_c_str};
+
+/// Combines stages of separable programs without relinking them,
+/// e.g. one vertex program with any number of fragment variants.
+pub struct ProgramPipeline {
+    gl: gl::Gl,
+    id: gl::types::GLuint,
+}
+
+impl ProgramPipeline {
+    pub fn new(gl: &gl::Gl) -> ProgramPipeline {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl.GenProgramPipelines(1, &mut id);
+        }
+
+        ProgramPipeline {
+            gl: gl.clone(),
+            id,
+        }
+    }
+
+    /// Uses every stage the program was linked with.
+    pub fn use_program(&self, program: &Program) -> Result<(), Error> {
+        self.use_program_stages(program.stages(), program)
+    }
+
+    /// Uses only the given stages (`gl::VERTEX_SHADER_BIT`, ...) of the program.
+    pub fn use_program_stages(&self, stages: gl::types::GLbitfield, program: &Program) -> Result<(), Error> {
+        if !program.is_separable() {
+            return Err(Error::NotSeparable { id: program.id() });
+        }
+
+        unsafe {
+            self.gl.UseProgramStages(self.id, stages, program.id());
+        }
+
+        Ok(())
+    }
+
+    pub fn clear_stages(&self, stages: gl::types::GLbitfield) {
+        unsafe {
+            self.gl.UseProgramStages(self.id, stages, 0);
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        use gl::types::{GLchar, GLint};
+
+        let mut success: GLint = 1;
+        unsafe {
+            self.gl.ValidateProgramPipeline(self.id);
+            self.gl.GetProgramPipelineiv(self.id, gl::VALIDATE_STATUS, &mut success);
+        }
+
+        if success == 0 {
+            let mut len: GLint = 0;
+            unsafe {
+                self.gl.GetProgramPipelineiv(self.id, gl::INFO_LOG_LENGTH, &mut len);
+            }
+            let err_msg: CString = whitespace_c_str(len as usize);
+            unsafe {
+                self.gl.GetProgramPipelineInfoLog(
+                    self.id,
+                    len,
+                    std::ptr::null_mut(),
+                    err_msg.as_ptr() as *mut GLchar
+                );
+            }
+            return Err(Error::PipelineValidation {
+                message: err_msg.to_string_lossy().into_owned()
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Binds the pipeline. A program bound with `Program::bind` takes
+    /// precedence over any pipeline, so it is unbound first.
+    pub fn bind(&self) {
+        unsafe {
+            self.gl.UseProgram(0);
+            self.gl.BindProgramPipeline(self.id);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            self.gl.BindProgramPipeline(0);
+        }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+}
+
+impl Drop for ProgramPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteProgramPipelines(1, &mut self.id);
+        }
+    }
+}
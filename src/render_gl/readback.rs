@@ -0,0 +1,48 @@
+use gl;
+use gl::types::{GLint, GLsizei, GLvoid};
+use image::DynamicImage;
+
+use render_gl::image_data::{ImageData, PixelData};
+use render_gl::viewport::Viewport;
+
+/// Reads the viewport area of the window's back buffer as RGBA8, top row
+/// first. Call it after rendering and before `gl_swap_window`, since the
+/// back buffer content is undefined after a swap.
+pub fn read_default_framebuffer(gl: &gl::Gl, viewport: &Viewport) -> DynamicImage {
+    unsafe {
+        gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        gl.ReadBuffer(gl::BACK);
+    }
+
+    read_pixels(gl, viewport.x, viewport.y, viewport.w as u32, viewport.h as u32)
+}
+
+/// Reads a rectangle of the current read buffer as RGBA8 and flips it
+/// so the top row comes first, as image files expect.
+pub(crate) fn read_pixels(gl: &gl::Gl, x: i32, y: i32, width: u32, height: u32) -> DynamicImage {
+    let mut data = vec![0u8; width as usize * height as usize * 4];
+
+    unsafe {
+        gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl.ReadPixels(
+            x as GLint,
+            y as GLint,
+            width as GLsizei,
+            height as GLsizei,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            data.as_mut_ptr() as *mut GLvoid
+        );
+        gl.PixelStorei(gl::PACK_ALIGNMENT, 4);
+    }
+
+    let mut img = ImageData {
+        width,
+        height,
+        channels: 4,
+        bgr: false,
+        data: PixelData::U8(data),
+    };
+    img.flip_vertically();
+    img.into_dynamic()
+}
@@ -0,0 +1,485 @@
+use gl;
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+
+use render_gl::sampler::CompareFunc;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DepthState {
+    pub func: CompareFunc,
+    /// False keeps the depth buffer as is, e.g. for transparent geometry
+    /// that should be hidden by opaque geometry but not hide anything itself.
+    pub write: bool,
+}
+
+impl DepthState {
+    pub fn less() -> DepthState {
+        DepthState { func: CompareFunc::Less, write: true }
+    }
+
+    pub fn read_only() -> DepthState {
+        DepthState { func: CompareFunc::LessOrEqual, write: false }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+    ConstantColor,
+    OneMinusConstantColor,
+    ConstantAlpha,
+    OneMinusConstantAlpha,
+    SrcAlphaSaturate,
+}
+
+impl BlendFactor {
+    fn gl_enum(&self) -> GLenum {
+        match *self {
+            BlendFactor::Zero => gl::ZERO,
+            BlendFactor::One => gl::ONE,
+            BlendFactor::SrcColor => gl::SRC_COLOR,
+            BlendFactor::OneMinusSrcColor => gl::ONE_MINUS_SRC_COLOR,
+            BlendFactor::DstColor => gl::DST_COLOR,
+            BlendFactor::OneMinusDstColor => gl::ONE_MINUS_DST_COLOR,
+            BlendFactor::SrcAlpha => gl::SRC_ALPHA,
+            BlendFactor::OneMinusSrcAlpha => gl::ONE_MINUS_SRC_ALPHA,
+            BlendFactor::DstAlpha => gl::DST_ALPHA,
+            BlendFactor::OneMinusDstAlpha => gl::ONE_MINUS_DST_ALPHA,
+            BlendFactor::ConstantColor => gl::CONSTANT_COLOR,
+            BlendFactor::OneMinusConstantColor => gl::ONE_MINUS_CONSTANT_COLOR,
+            BlendFactor::ConstantAlpha => gl::CONSTANT_ALPHA,
+            BlendFactor::OneMinusConstantAlpha => gl::ONE_MINUS_CONSTANT_ALPHA,
+            BlendFactor::SrcAlphaSaturate => gl::SRC_ALPHA_SATURATE,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendEquation {
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl BlendEquation {
+    fn gl_enum(&self) -> GLenum {
+        match *self {
+            BlendEquation::Add => gl::FUNC_ADD,
+            BlendEquation::Subtract => gl::FUNC_SUBTRACT,
+            BlendEquation::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+            BlendEquation::Min => gl::MIN,
+            BlendEquation::Max => gl::MAX,
+        }
+    }
+}
+
+/// `result = src * src_factor <equation> dst * dst_factor`, separately for
+/// color and alpha.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BlendState {
+    pub color_equation: BlendEquation,
+    pub src_color: BlendFactor,
+    pub dst_color: BlendFactor,
+    pub alpha_equation: BlendEquation,
+    pub src_alpha: BlendFactor,
+    pub dst_alpha: BlendFactor,
+    pub constant: [f32; 4],
+}
+
+impl BlendState {
+    pub fn new(equation: BlendEquation, src: BlendFactor, dst: BlendFactor) -> BlendState {
+        BlendState {
+            color_equation: equation,
+            src_color: src,
+            dst_color: dst,
+            alpha_equation: equation,
+            src_alpha: src,
+            dst_alpha: dst,
+            constant: [0.0; 4],
+        }
+    }
+
+    /// Classic transparency with straight (not premultiplied) alpha.
+    pub fn alpha() -> BlendState {
+        let mut state = BlendState::new(BlendEquation::Add, BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha);
+        state.src_alpha = BlendFactor::One;
+        state
+    }
+
+    pub fn premultiplied_alpha() -> BlendState {
+        BlendState::new(BlendEquation::Add, BlendFactor::One, BlendFactor::OneMinusSrcAlpha)
+    }
+
+    /// Glow, particles and light accumulation.
+    pub fn additive() -> BlendState {
+        BlendState::new(BlendEquation::Add, BlendFactor::One, BlendFactor::One)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CullFace {
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+impl CullFace {
+    fn gl_enum(&self) -> GLenum {
+        match *self {
+            CullFace::Front => gl::FRONT,
+            CullFace::Back => gl::BACK,
+            CullFace::FrontAndBack => gl::FRONT_AND_BACK,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FrontFace {
+    CounterClockwise,
+    Clockwise,
+}
+
+impl FrontFace {
+    fn gl_enum(&self) -> GLenum {
+        match *self {
+            FrontFace::CounterClockwise => gl::CCW,
+            FrontFace::Clockwise => gl::CW,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    Increment,
+    IncrementWrap,
+    Decrement,
+    DecrementWrap,
+    Invert,
+}
+
+impl StencilOp {
+    fn gl_enum(&self) -> GLenum {
+        match *self {
+            StencilOp::Keep => gl::KEEP,
+            StencilOp::Zero => gl::ZERO,
+            StencilOp::Replace => gl::REPLACE,
+            StencilOp::Increment => gl::INCR,
+            StencilOp::IncrementWrap => gl::INCR_WRAP,
+            StencilOp::Decrement => gl::DECR,
+            StencilOp::DecrementWrap => gl::DECR_WRAP,
+            StencilOp::Invert => gl::INVERT,
+        }
+    }
+}
+
+/// Stencil test and update for one winding; the test passes when
+/// `reference & read_mask <func> stored & read_mask`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StencilFace {
+    pub func: CompareFunc,
+    pub reference: i32,
+    pub read_mask: u32,
+    pub write_mask: u32,
+    pub stencil_fail: StencilOp,
+    pub depth_fail: StencilOp,
+    pub pass: StencilOp,
+}
+
+impl StencilFace {
+    /// Writes `reference` wherever something is drawn, e.g. to mark a mirror or portal.
+    pub fn write(reference: i32) -> StencilFace {
+        StencilFace {
+            func: CompareFunc::Always,
+            reference,
+            read_mask: 0xFF,
+            write_mask: 0xFF,
+            stencil_fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Replace,
+        }
+    }
+
+    /// Draws only where the stencil buffer holds `reference`, leaving it unchanged.
+    pub fn test_equal(reference: i32) -> StencilFace {
+        StencilFace {
+            func: CompareFunc::Equal,
+            reference,
+            read_mask: 0xFF,
+            write_mask: 0x00,
+            stencil_fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StencilState {
+    pub front: StencilFace,
+    pub back: StencilFace,
+}
+
+impl StencilState {
+    pub fn both(face: StencilFace) -> StencilState {
+        StencilState { front: face, back: face }
+    }
+}
+
+/// Rectangle in framebuffer pixels, from the bottom-left corner like `Viewport`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ScissorRect {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point,
+}
+
+impl PolygonMode {
+    fn gl_enum(&self) -> GLenum {
+        match *self {
+            PolygonMode::Fill => gl::FILL,
+            PolygonMode::Line => gl::LINE,
+            PolygonMode::Point => gl::POINT,
+        }
+    }
+}
+
+/// Fixed-function state of a draw call. `None` disables the test or stage;
+/// `RenderState::default()` matches the initial state of a GL context.
+///
+/// Note that clearing is affected too: `depth.write`, the stencil write mask,
+/// `color_mask` and `scissor` also limit what `glClear` touches.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RenderState {
+    pub depth: Option<DepthState>,
+    pub blend: Option<BlendState>,
+    pub cull: Option<CullFace>,
+    pub front_face: FrontFace,
+    pub stencil: Option<StencilState>,
+    pub scissor: Option<ScissorRect>,
+    pub color_mask: [bool; 4],
+    pub polygon_mode: PolygonMode,
+}
+
+impl Default for RenderState {
+    fn default() -> RenderState {
+        RenderState {
+            depth: None,
+            blend: None,
+            cull: None,
+            front_face: FrontFace::CounterClockwise,
+            stencil: None,
+            scissor: None,
+            color_mask: [true; 4],
+            polygon_mode: PolygonMode::Fill,
+        }
+    }
+}
+
+impl RenderState {
+    /// Depth tested and written, back faces culled.
+    pub fn opaque() -> RenderState {
+        RenderState::default()
+            .depth(DepthState::less())
+            .cull(CullFace::Back)
+    }
+
+    /// Alpha blended, depth tested against opaque geometry without writing.
+    pub fn transparent() -> RenderState {
+        RenderState::default()
+            .depth(DepthState::read_only())
+            .blend(BlendState::alpha())
+    }
+
+    pub fn depth(mut self, depth: DepthState) -> RenderState {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn blend(mut self, blend: BlendState) -> RenderState {
+        self.blend = Some(blend);
+        self
+    }
+
+    pub fn cull(mut self, cull: CullFace) -> RenderState {
+        self.cull = Some(cull);
+        self
+    }
+
+    pub fn front_face(mut self, front_face: FrontFace) -> RenderState {
+        self.front_face = front_face;
+        self
+    }
+
+    pub fn stencil(mut self, stencil: StencilState) -> RenderState {
+        self.stencil = Some(stencil);
+        self
+    }
+
+    pub fn scissor(mut self, scissor: ScissorRect) -> RenderState {
+        self.scissor = Some(scissor);
+        self
+    }
+
+    pub fn color_mask(mut self, r: bool, g: bool, b: bool, a: bool) -> RenderState {
+        self.color_mask = [r, g, b, a];
+        self
+    }
+
+    pub fn polygon_mode(mut self, polygon_mode: PolygonMode) -> RenderState {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+}
+
+/// Remembers the state last set on the context and issues GL calls only
+/// for the parts of a new `RenderState` that differ from it.
+///
+/// Code that changes the same state directly must call `invalidate`
+/// afterwards, or the tracker will skip calls it believes are redundant.
+pub struct StateTracker {
+    gl: gl::Gl,
+    current: RenderState,
+    valid: bool,
+}
+
+impl StateTracker {
+    /// Assumes the context is still in its initial state.
+    pub fn new(gl: &gl::Gl) -> StateTracker {
+        StateTracker {
+            gl: gl.clone(),
+            current: RenderState::default(),
+            valid: true,
+        }
+    }
+
+    /// State as the tracker last applied it.
+    pub fn current(&self) -> &RenderState {
+        &self.current
+    }
+
+    /// Forgets the known state; the next `apply` sets everything.
+    pub fn invalidate(&mut self) {
+        self.valid = false;
+    }
+
+    pub fn apply(&mut self, state: &RenderState) {
+        let force = !self.valid;
+        let old = self.current;
+        let gl = &self.gl;
+
+        unsafe {
+            if force || state.depth != old.depth {
+                match state.depth {
+                    Some(depth) => {
+                        gl.Enable(gl::DEPTH_TEST);
+                        gl.DepthFunc(depth.func.gl_enum());
+                        gl.DepthMask(gl_bool(depth.write));
+                    },
+                    None => {
+                        gl.Disable(gl::DEPTH_TEST);
+                        gl.DepthMask(gl::TRUE);
+                    },
+                }
+            }
+
+            if force || state.blend != old.blend {
+                match state.blend {
+                    Some(blend) => {
+                        gl.Enable(gl::BLEND);
+                        gl.BlendEquationSeparate(blend.color_equation.gl_enum(), blend.alpha_equation.gl_enum());
+                        gl.BlendFuncSeparate(
+                            blend.src_color.gl_enum(),
+                            blend.dst_color.gl_enum(),
+                            blend.src_alpha.gl_enum(),
+                            blend.dst_alpha.gl_enum()
+                        );
+                        let c = blend.constant;
+                        gl.BlendColor(c[0], c[1], c[2], c[3]);
+                    },
+                    None => gl.Disable(gl::BLEND),
+                }
+            }
+
+            if force || state.cull != old.cull {
+                match state.cull {
+                    Some(cull) => {
+                        gl.Enable(gl::CULL_FACE);
+                        gl.CullFace(cull.gl_enum());
+                    },
+                    None => gl.Disable(gl::CULL_FACE),
+                }
+            }
+
+            if force || state.front_face != old.front_face {
+                gl.FrontFace(state.front_face.gl_enum());
+            }
+
+            if force || state.stencil != old.stencil {
+                match state.stencil {
+                    Some(stencil) => {
+                        gl.Enable(gl::STENCIL_TEST);
+                        apply_stencil_face(gl, gl::FRONT, &stencil.front);
+                        apply_stencil_face(gl, gl::BACK, &stencil.back);
+                    },
+                    None => {
+                        gl.Disable(gl::STENCIL_TEST);
+                        gl.StencilMask(!0);
+                    },
+                }
+            }
+
+            if force || state.scissor != old.scissor {
+                match state.scissor {
+                    Some(rect) => {
+                        gl.Enable(gl::SCISSOR_TEST);
+                        gl.Scissor(rect.x, rect.y, rect.w as GLsizei, rect.h as GLsizei);
+                    },
+                    None => gl.Disable(gl::SCISSOR_TEST),
+                }
+            }
+
+            if force || state.color_mask != old.color_mask {
+                let m = state.color_mask;
+                gl.ColorMask(gl_bool(m[0]), gl_bool(m[1]), gl_bool(m[2]), gl_bool(m[3]));
+            }
+
+            if force || state.polygon_mode != old.polygon_mode {
+                gl.PolygonMode(gl::FRONT_AND_BACK, state.polygon_mode.gl_enum());
+            }
+        }
+
+        self.current = *state;
+        self.valid = true;
+    }
+}
+
+unsafe fn apply_stencil_face(gl: &gl::Gl, face: GLenum, state: &StencilFace) {
+    gl.StencilFuncSeparate(face, state.func.gl_enum(), state.reference as GLint, state.read_mask as GLuint);
+    gl.StencilMaskSeparate(face, state.write_mask as GLuint);
+    gl.StencilOpSeparate(face, state.stencil_fail.gl_enum(), state.depth_fail.gl_enum(), state.pass.gl_enum());
+}
+
+fn gl_bool(value: bool) -> gl::types::GLboolean {
+    if value { gl::TRUE } else { gl::FALSE }
+}
@@ -0,0 +1,166 @@
+use gl;
+use gl::types::{GLenum, GLfloat, GLint, GLuint};
+
+use render_gl::texture::{MinFilter, MagFilter, Wrap};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CompareFunc {
+    Never,
+    Less,
+    Equal,
+    LessOrEqual,
+    Greater,
+    NotEqual,
+    GreaterOrEqual,
+    Always,
+}
+
+impl CompareFunc {
+    pub(crate) fn gl_enum(&self) -> GLenum {
+        match *self {
+            CompareFunc::Never => gl::NEVER,
+            CompareFunc::Less => gl::LESS,
+            CompareFunc::Equal => gl::EQUAL,
+            CompareFunc::LessOrEqual => gl::LEQUAL,
+            CompareFunc::Greater => gl::GREATER,
+            CompareFunc::NotEqual => gl::NOTEQUAL,
+            CompareFunc::GreaterOrEqual => gl::GEQUAL,
+            CompareFunc::Always => gl::ALWAYS,
+        }
+    }
+}
+
+/// Sampling state kept apart from texture data. A sampler bound to a unit
+/// overrides the parameters of whatever texture is bound to the same unit,
+/// so one texture can be read with different filtering by different passes.
+pub struct Sampler {
+    gl: gl::Gl,
+    id: GLuint,
+}
+
+impl Sampler {
+    pub fn new(gl: &gl::Gl) -> Sampler {
+        let mut id: GLuint = 0;
+        unsafe {
+            gl.GenSamplers(1, &mut id);
+        }
+
+        Sampler {
+            gl: gl.clone(),
+            id,
+        }
+    }
+
+    /// Unfiltered, clamped lookups for pixel art and lookup tables.
+    pub fn nearest(gl: &gl::Gl) -> Sampler {
+        let sampler = Sampler::new(gl);
+        sampler.set_min_filter(MinFilter::Nearest);
+        sampler.set_mag_filter(MagFilter::Nearest);
+        sampler.set_wrap(Wrap::ClampToEdge);
+        sampler
+    }
+
+    /// Linear filtering between and within mip levels, repeating.
+    pub fn trilinear(gl: &gl::Gl) -> Sampler {
+        let sampler = Sampler::new(gl);
+        sampler.set_min_filter(MinFilter::LinearMipmapLinear);
+        sampler.set_mag_filter(MagFilter::Linear);
+        sampler.set_wrap(Wrap::Repeat);
+        sampler
+    }
+
+    /// Trilinear filtering plus anisotropy, clamped to the driver maximum.
+    pub fn anisotropic(gl: &gl::Gl, level: f32) -> Sampler {
+        let sampler = Sampler::trilinear(gl);
+        sampler.set_anisotropy(level);
+        sampler
+    }
+
+    /// Depth comparison sampler for `sampler2DShadow` lookups. Texels outside
+    /// the shadow map compare against a border depth of 1.0, i.e. lit.
+    pub fn shadow(gl: &gl::Gl) -> Sampler {
+        let sampler = Sampler::new(gl);
+        sampler.set_min_filter(MinFilter::Linear);
+        sampler.set_mag_filter(MagFilter::Linear);
+        sampler.set_wrap(Wrap::ClampToBorder);
+        sampler.set_border_color([1.0, 1.0, 1.0, 1.0]);
+        sampler.set_compare(Some(CompareFunc::LessOrEqual));
+        sampler
+    }
+
+    pub fn set_min_filter(&self, filter: MinFilter) {
+        self.set_parameter_i(gl::TEXTURE_MIN_FILTER, filter.gl_enum());
+    }
+
+    pub fn set_mag_filter(&self, filter: MagFilter) {
+        self.set_parameter_i(gl::TEXTURE_MAG_FILTER, filter.gl_enum());
+    }
+
+    pub fn set_wrap(&self, wrap: Wrap) {
+        self.set_parameter_i(gl::TEXTURE_WRAP_S, wrap.gl_enum());
+        self.set_parameter_i(gl::TEXTURE_WRAP_T, wrap.gl_enum());
+        self.set_parameter_i(gl::TEXTURE_WRAP_R, wrap.gl_enum());
+    }
+
+    pub fn set_border_color(&self, color: [f32; 4]) {
+        unsafe {
+            self.gl.SamplerParameterfv(self.id, gl::TEXTURE_BORDER_COLOR, color.as_ptr() as *const GLfloat);
+        }
+    }
+
+    pub fn set_anisotropy(&self, level: f32) {
+        let mut max_level: GLfloat = 1.0;
+        unsafe {
+            self.gl.GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut max_level);
+            self.gl.SamplerParameterf(self.id, gl::TEXTURE_MAX_ANISOTROPY_EXT, level.max(1.0).min(max_level));
+        }
+    }
+
+    /// `Some` enables depth comparison with the given function, `None` disables it.
+    pub fn set_compare(&self, func: Option<CompareFunc>) {
+        match func {
+            Some(func) => {
+                self.set_parameter_i(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE);
+                self.set_parameter_i(gl::TEXTURE_COMPARE_FUNC, func.gl_enum());
+            },
+            None => self.set_parameter_i(gl::TEXTURE_COMPARE_MODE, gl::NONE),
+        }
+    }
+
+    pub fn set_lod_range(&self, min_lod: f32, max_lod: f32) {
+        unsafe {
+            self.gl.SamplerParameterf(self.id, gl::TEXTURE_MIN_LOD, min_lod);
+            self.gl.SamplerParameterf(self.id, gl::TEXTURE_MAX_LOD, max_lod);
+        }
+    }
+
+    fn set_parameter_i(&self, name: GLenum, value: GLenum) {
+        unsafe {
+            self.gl.SamplerParameteri(self.id, name, value as GLint);
+        }
+    }
+
+    pub fn bind(&self, slot: u32) {
+        unsafe {
+            self.gl.BindSampler(slot as GLuint, self.id);
+        }
+    }
+
+    pub fn unbind(&self, slot: u32) {
+        unsafe {
+            self.gl.BindSampler(slot as GLuint, 0);
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteSamplers(1, &mut self.id);
+        }
+    }
+}
@@ -28,19 +28,50 @@ pub enum Error {
         name: String,
         message: String
     },
+
+    #[fail(display = "Program {} was not linked as separable", id)]
+    NotSeparable {
+        id: gl::types::GLuint
+    },
+
+    #[fail(display = "Program has no active resource named {}", name)]
+    ResourceNotFound {
+        name: String
+    },
+
+    #[fail(display = "Program pipeline validation failed: {}", message)]
+    PipelineValidation {
+        message: String
+    },
 }
 
 pub struct Program {
-    gl: gl::Gl,
+    pub(crate) gl: gl::Gl,
     id: gl::types::GLuint,
+    stages: gl::types::GLbitfield,
+    separable: bool,
 }
 
 impl Program {
     pub fn from_shaders(gl: &gl::Gl, shaders: &[Shader]) -> Result<Program, String> {
+        Program::link(gl, shaders, false)
+    }
+
+    /// Links the shaders with `PROGRAM_SEPARABLE` set, so the result can be
+    /// mixed with other separable programs in a `ProgramPipeline`.
+    pub fn separable_from_shaders(gl: &gl::Gl, shaders: &[Shader]) -> Result<Program, String> {
+        Program::link(gl, shaders, true)
+    }
+
+    fn link(gl: &gl::Gl, shaders: &[Shader], separable: bool) -> Result<Program, String> {
         use gl::types::{GLchar, GLint, GLuint};
 
         let program_id: GLuint = unsafe { gl.CreateProgram() };
 
+        if separable {
+            unsafe { gl.ProgramParameteri(program_id, gl::PROGRAM_SEPARABLE, gl::TRUE as GLint); }
+        }
+
         for shader in shaders {
             unsafe { gl.AttachShader(program_id, shader.id()); }
         }
@@ -73,7 +104,15 @@ impl Program {
             unsafe { gl.DetachShader(program_id, shader.id()); }
         }
 
-        Ok(Program { gl: gl.clone(), id: program_id })
+        let stages = shaders.iter()
+            .fold(0, |stages, shader| stages | shader.stage_bit());
+
+        Ok(Program {
+            gl: gl.clone(),
+            id: program_id,
+            stages,
+            separable,
+        })
     }
 
     pub fn from_res(gl: &gl::Gl, res: &Resources, name: &str) -> Result<Program, Error> {
@@ -95,6 +134,29 @@ impl Program {
             })
     }
 
+    /// Loads `<name>.comp.shader` and links it into a compute program.
+    pub fn compute_from_res(gl: &gl::Gl, res: &Resources, name: &str) -> Result<Program, Error> {
+        let shader = Shader::from_res(gl, res, &format!("{}.comp.shader", name))?;
+
+        Program::from_shaders(gl, &[shader])
+            .map_err(|message| Error::LinkError {
+                name: name.into(),
+                message
+            })
+    }
+
+    /// Loads a single shader stage, e.g. `shaders/triangle.frag.shader`,
+    /// and links it into a separable program.
+    pub fn separable_from_res(gl: &gl::Gl, res: &Resources, name: &str) -> Result<Program, Error> {
+        let shader = Shader::from_res(gl, res, name)?;
+
+        Program::separable_from_shaders(gl, &[shader])
+            .map_err(|message| Error::LinkError {
+                name: name.into(),
+                message
+            })
+    }
+
     pub fn bind(&self) {
         unsafe {
             self.gl.UseProgram(self.id);
@@ -107,11 +169,30 @@ impl Program {
         }
     }
 
+    // uniforms are written with ProgramUniform* so they reach the right
+    // program even when it is only one stage of a bound pipeline
+
     pub fn set_uniform_1i(&self, name: &str, val: i32) {
         let u_loc = self.uniform_location(name);
 
         unsafe {
-            self.gl.Uniform1i(u_loc, val as gl::types::GLint);
+            self.gl.ProgramUniform1i(self.id, u_loc, val as gl::types::GLint);
+        }
+    }
+
+    pub fn set_uniform_1f(&self, name: &str, val: f32) {
+        let u_loc = self.uniform_location(name);
+
+        unsafe {
+            self.gl.ProgramUniform1f(self.id, u_loc, val as gl::types::GLfloat);
+        }
+    }
+
+    pub fn set_uniform_2f(&self, name: &str, x: f32, y: f32) {
+        let u_loc = self.uniform_location(name);
+
+        unsafe {
+            self.gl.ProgramUniform2f(self.id, u_loc, x as gl::types::GLfloat, y as gl::types::GLfloat);
         }
     }
 
@@ -119,7 +200,8 @@ impl Program {
         let u_loc = self.uniform_location(name);
 
         unsafe {
-            self.gl.UniformMatrix4fv(
+            self.gl.ProgramUniformMatrix4fv(
+                self.id,
                 u_loc,
                 1,
                 gl::FALSE,
@@ -128,7 +210,7 @@ impl Program {
         }
     }
 
-    fn uniform_location(&self, name: &str) -> gl::types::GLint {
+    pub(crate) fn uniform_location(&self, name: &str) -> gl::types::GLint {
         use gl::types::{GLint};
 
         let location: GLint = unsafe {
@@ -146,6 +228,15 @@ impl Program {
     pub fn id(&self) -> gl::types::GLuint {
         self.id
     }
+
+    /// Stage bits (`VERTEX_SHADER_BIT`, ...) of the shaders linked into this program.
+    pub fn stages(&self) -> gl::types::GLbitfield {
+        self.stages
+    }
+
+    pub fn is_separable(&self) -> bool {
+        self.separable
+    }
 }
 
 impl Drop for Program {
@@ -159,12 +250,13 @@ impl Drop for Program {
 pub struct Shader {
     gl: gl::Gl,
     id: gl::types::GLuint,
+    kind: gl::types::GLenum,
 }
 
 impl Shader {
     pub fn from_source(gl: &gl::Gl, source: &CStr, shader_type: gl::types::GLenum) -> Result<Shader, String> {
         let id = shader_from_source(&gl, source, shader_type)?;
-        Ok(Shader { gl: gl.clone(), id })
+        Ok(Shader { gl: gl.clone(), id, kind: shader_type })
     }
 
     pub fn from_vert_source(gl: &gl::Gl, source: &CStr) -> Result<Shader, String> {
@@ -176,9 +268,10 @@ impl Shader {
     }
 
     pub fn from_res(gl: &gl::Gl, res: &Resources, name: &str) -> Result<Shader, Error> {
-        const POSSIBLE_EXT: [(&str, gl::types::GLenum); 2] = [
+        const POSSIBLE_EXT: [(&str, gl::types::GLenum); 3] = [
             (".vert.shader", gl::VERTEX_SHADER),
             (".frag.shader", gl::FRAGMENT_SHADER),
+            (".comp.shader", gl::COMPUTE_SHADER),
         ];
 
         let shader_type = POSSIBLE_EXT.iter()
@@ -202,6 +295,22 @@ impl Shader {
     pub fn id(&self) -> gl::types::GLuint {
         self.id
     }
+
+    pub fn kind(&self) -> gl::types::GLenum {
+        self.kind
+    }
+
+    fn stage_bit(&self) -> gl::types::GLbitfield {
+        match self.kind {
+            gl::VERTEX_SHADER => gl::VERTEX_SHADER_BIT,
+            gl::FRAGMENT_SHADER => gl::FRAGMENT_SHADER_BIT,
+            gl::GEOMETRY_SHADER => gl::GEOMETRY_SHADER_BIT,
+            gl::TESS_CONTROL_SHADER => gl::TESS_CONTROL_SHADER_BIT,
+            gl::TESS_EVALUATION_SHADER => gl::TESS_EVALUATION_SHADER_BIT,
+            gl::COMPUTE_SHADER => gl::COMPUTE_SHADER_BIT,
+            _ => 0,
+        }
+    }
 }
 
 impl Drop for Shader {
@@ -1,45 +1,423 @@
 use gl;
+use gl::types::{GLenum, GLfloat, GLint, GLuint};
 
-use resources::Resources;
+use image::{self, DynamicImage};
 
-pub struct Texture {
-    gl: gl::Gl,
-    id: gl::types::GLuint,
+use render_gl::compressed::CompressedFormat;
+use render_gl::image_data::{ImageData, PixelData, PixelLayout};
+use render_gl::sampler::Sampler;
+use resources::{self, Resources};
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Unable to load texture {}", name)]
+    ResourceLoad {
+        name: String,
+        #[cause] inner: resources::Error
+    },
+
+    #[fail(display = "Unable to decode image {}", name)]
+    ImageDecode {
+        name: String,
+        #[cause] inner: image::ImageError
+    },
+
+    #[fail(display = "Image {} of {}x{} is not a cube map cross, expected a 4:3 or 3:4 layout", name, width, height)]
+    InvalidCrossLayout {
+        name: String,
+        width: u32,
+        height: u32
+    },
+
+    #[fail(display = "No images given for a layered texture")]
+    NoImages,
+
+    #[fail(display = "Image {} differs in size or pixel format from the first layer", name)]
+    ImageMismatch {
+        name: String
+    },
+
+    #[fail(display = "Invalid compressed texture {}: {}", name, message)]
+    InvalidContainer {
+        name: String,
+        message: String
+    },
+
+    #[fail(display = "Texture {} uses {:?}, which neither the driver nor the CPU fallback can decode", name, format)]
+    UnsupportedFormat {
+        name: String,
+        format: CompressedFormat
+    },
+
+    #[fail(display = "Image {} of {}x{} does not fit an atlas page of {}x{} with its padding", name, width, height, max_size, max_size)]
+    AtlasImageTooLarge {
+        name: String,
+        width: u32,
+        height: u32,
+        max_size: u32
+    },
+
+    #[fail(display = "Sub-image data has {} bytes, expected {}", actual, expected)]
+    SubImageSize {
+        expected: usize,
+        actual: usize
+    },
+
+    #[fail(display = "Image pixel layout does not match the texture's")]
+    LayoutMismatch,
+
+    #[fail(display = "Texture has no known pixel layout to update, e.g. because it is compressed")]
+    NotUpdatable,
+
+    #[fail(display = "Region {}x{} at {},{} is outside the {}x{} texture", width, height, x, y, texture_width, texture_height)]
+    RegionOutOfBounds {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        texture_width: u32,
+        texture_height: u32
+    },
+
+    #[fail(display = "Unable to map the texture streaming buffer")]
+    StreamMapFailed,
 }
 
-impl Texture {
-    pub fn from_res(gl: &gl::Gl, res: &Resources, name: &str) -> Result<Texture, String> {
-        use gl::types::{GLint, GLuint, GLvoid};
-        use image::GenericImageView;
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MinFilter {
+    Nearest,
+    Linear,
+    NearestMipmapNearest,
+    LinearMipmapNearest,
+    NearestMipmapLinear,
+    LinearMipmapLinear,
+}
+
+impl MinFilter {
+    pub(crate) fn gl_enum(&self) -> GLenum {
+        match *self {
+            MinFilter::Nearest => gl::NEAREST,
+            MinFilter::Linear => gl::LINEAR,
+            MinFilter::NearestMipmapNearest => gl::NEAREST_MIPMAP_NEAREST,
+            MinFilter::LinearMipmapNearest => gl::LINEAR_MIPMAP_NEAREST,
+            MinFilter::NearestMipmapLinear => gl::NEAREST_MIPMAP_LINEAR,
+            MinFilter::LinearMipmapLinear => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
 
-        let img = res.load_image(name).unwrap();
+    pub fn uses_mipmaps(&self) -> bool {
+        match *self {
+            MinFilter::Nearest | MinFilter::Linear => false,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MagFilter {
+    Nearest,
+    Linear,
+}
+
+impl MagFilter {
+    pub(crate) fn gl_enum(&self) -> GLenum {
+        match *self {
+            MagFilter::Nearest => gl::NEAREST,
+            MagFilter::Linear => gl::LINEAR,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+    MirrorClampToEdge,
+}
 
+impl Wrap {
+    pub(crate) fn gl_enum(&self) -> GLenum {
+        match *self {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            Wrap::ClampToBorder => gl::CLAMP_TO_BORDER,
+            Wrap::MirrorClampToEdge => gl::MIRROR_CLAMP_TO_EDGE,
+        }
+    }
+}
+
+/// Describes how a texture is created. Defaults are linear filtering,
+/// clamped edges, no mipmaps, a linear (non-sRGB) internal format and
+/// rows flipped so the first image row ends up at `v = 1`.
+#[derive(Clone, Debug)]
+pub struct TextureBuilder {
+    min_filter: MinFilter,
+    mag_filter: MagFilter,
+    wrap_s: Wrap,
+    wrap_t: Wrap,
+    wrap_r: Wrap,
+    border_color: [f32; 4],
+    mipmaps: bool,
+    anisotropy: Option<f32>,
+    srgb: bool,
+    flip_vertically: bool,
+}
+
+impl TextureBuilder {
+    pub fn new() -> TextureBuilder {
+        TextureBuilder {
+            min_filter: MinFilter::Linear,
+            mag_filter: MagFilter::Linear,
+            wrap_s: Wrap::ClampToEdge,
+            wrap_t: Wrap::ClampToEdge,
+            wrap_r: Wrap::ClampToEdge,
+            border_color: [0.0, 0.0, 0.0, 0.0],
+            mipmaps: false,
+            anisotropy: None,
+            srgb: false,
+            flip_vertically: true,
+        }
+    }
+
+    pub fn min_filter(mut self, filter: MinFilter) -> TextureBuilder {
+        self.min_filter = filter;
+        self
+    }
+
+    pub fn mag_filter(mut self, filter: MagFilter) -> TextureBuilder {
+        self.mag_filter = filter;
+        self
+    }
+
+    /// Sets the same wrap mode for every axis.
+    pub fn wrap(mut self, wrap: Wrap) -> TextureBuilder {
+        self.wrap_s = wrap;
+        self.wrap_t = wrap;
+        self.wrap_r = wrap;
+        self
+    }
+
+    pub fn wrap_s(mut self, wrap: Wrap) -> TextureBuilder {
+        self.wrap_s = wrap;
+        self
+    }
+
+    pub fn wrap_t(mut self, wrap: Wrap) -> TextureBuilder {
+        self.wrap_t = wrap;
+        self
+    }
+
+    pub fn wrap_r(mut self, wrap: Wrap) -> TextureBuilder {
+        self.wrap_r = wrap;
+        self
+    }
+
+    /// Color used by `Wrap::ClampToBorder`.
+    pub fn border_color(mut self, color: [f32; 4]) -> TextureBuilder {
+        self.border_color = color;
+        self
+    }
+
+    /// Generates the full mip chain after upload. Mipmaps are also generated
+    /// whenever the min filter samples them.
+    pub fn mipmaps(mut self, mipmaps: bool) -> TextureBuilder {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    /// Requested anisotropy level, clamped to what the driver supports.
+    pub fn anisotropy(mut self, level: f32) -> TextureBuilder {
+        self.anisotropy = Some(level);
+        self
+    }
+
+    /// Stores the texels as sRGB so sampling returns linear values.
+    /// Use it for color maps, leave it off for normal maps and other data.
+    pub fn srgb(mut self, srgb: bool) -> TextureBuilder {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Flips rows on load. GL's texture origin is bottom-left while image
+    /// files start at the top; turn it off for data already stored bottom-up.
+    pub fn flip_vertically(mut self, flip: bool) -> TextureBuilder {
+        self.flip_vertically = flip;
+        self
+    }
+
+    pub fn from_res(&self, gl: &gl::Gl, res: &Resources, name: &str) -> Result<Texture, Error> {
+        let img = self.load_image_data(res, name)?;
+        Ok(self.from_image_data(gl, &img))
+    }
+
+    /// Uploads already decoded pixels as is; `flip_vertically` is not applied.
+    pub fn from_image_data(&self, gl: &gl::Gl, img: &ImageData) -> Texture {
         let mut texture_id: GLuint = 0;
         unsafe {
             gl.GenTextures(1, &mut texture_id);
             gl.BindTexture(gl::TEXTURE_2D, texture_id);
-            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
-            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
-            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
-            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+        }
+
+        self.apply_parameters(gl, gl::TEXTURE_2D);
+        apply_swizzle(gl, gl::TEXTURE_2D, img);
+
+        unsafe {
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
             gl.TexImage2D(
                 gl::TEXTURE_2D,
                 0,
-                gl::RGBA8 as GLint,
-                img.width() as GLint,
-                img.height() as GLint,
+                self.internal_format(img) as GLint,
+                img.width as GLint,
+                img.height as GLint,
                 0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                img.raw_pixels().as_ptr() as *const GLvoid
+                img.gl_format(),
+                img.gl_type(),
+                img.as_ptr()
             );
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        }
+
+        self.generate_mipmaps(gl, gl::TEXTURE_2D);
+
+        unsafe {
             gl.BindTexture(gl::TEXTURE_2D, 0);
         }
 
-        Ok(Texture {
+        Texture::from_raw(gl, texture_id, img.width, img.height, Some(img.layout()))
+    }
+
+    /// Decodes an image and applies `flip_vertically`.
+    pub(crate) fn load_image_data(&self, res: &Resources, name: &str) -> Result<ImageData, Error> {
+        let mut img = ImageData::from_res(res, name)?;
+        if self.flip_vertically {
+            img.flip_vertically();
+        }
+
+        Ok(img)
+    }
+
+    pub(crate) fn internal_format(&self, img: &ImageData) -> GLenum {
+        img.internal_format(self.srgb)
+    }
+
+    /// Writes the sampling parameters to the texture bound to `target`.
+    pub(crate) fn apply_parameters(&self, gl: &gl::Gl, target: GLenum) {
+        unsafe {
+            gl.TexParameteri(target, gl::TEXTURE_MIN_FILTER, self.min_filter.gl_enum() as GLint);
+            gl.TexParameteri(target, gl::TEXTURE_MAG_FILTER, self.mag_filter.gl_enum() as GLint);
+            gl.TexParameteri(target, gl::TEXTURE_WRAP_S, self.wrap_s.gl_enum() as GLint);
+            gl.TexParameteri(target, gl::TEXTURE_WRAP_T, self.wrap_t.gl_enum() as GLint);
+            gl.TexParameteri(target, gl::TEXTURE_WRAP_R, self.wrap_r.gl_enum() as GLint);
+            gl.TexParameterfv(target, gl::TEXTURE_BORDER_COLOR, self.border_color.as_ptr() as *const GLfloat);
+        }
+
+        if let Some(level) = self.anisotropy {
+            let mut max_level: GLfloat = 1.0;
+            unsafe {
+                gl.GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut max_level);
+                gl.TexParameterf(target, gl::TEXTURE_MAX_ANISOTROPY_EXT, level.max(1.0).min(max_level));
+            }
+        }
+    }
+
+    pub(crate) fn generate_mipmaps(&self, gl: &gl::Gl, target: GLenum) {
+        if self.mipmaps || self.min_filter.uses_mipmaps() {
+            unsafe {
+                gl.GenerateMipmap(target);
+            }
+        }
+    }
+}
+
+/// Client pixel layout that matches a sized internal format, for the
+/// formats render targets commonly use.
+fn layout_for_internal_format(internal_format: GLenum) -> Option<PixelLayout> {
+    let (format, pixel_type, bytes_per_pixel) = match internal_format {
+        gl::R8 => (gl::RED, gl::UNSIGNED_BYTE, 1),
+        gl::RG8 => (gl::RG, gl::UNSIGNED_BYTE, 2),
+        gl::RGB8 | gl::SRGB8 => (gl::RGB, gl::UNSIGNED_BYTE, 3),
+        gl::RGBA8 | gl::SRGB8_ALPHA8 => (gl::RGBA, gl::UNSIGNED_BYTE, 4),
+        gl::R16F | gl::R32F => (gl::RED, gl::FLOAT, 4),
+        gl::RG16F | gl::RG32F => (gl::RG, gl::FLOAT, 8),
+        gl::RGB16F | gl::RGB32F => (gl::RGB, gl::FLOAT, 12),
+        gl::RGBA16F | gl::RGBA32F => (gl::RGBA, gl::FLOAT, 16),
+        _ => return None,
+    };
+
+    Some(PixelLayout {
+        format,
+        pixel_type,
+        bytes_per_pixel,
+    })
+}
+
+/// Makes gray and gray-alpha images sample as gray rather than red.
+pub(crate) fn apply_swizzle(gl: &gl::Gl, target: GLenum, img: &ImageData) {
+    if let Some(swizzle) = img.swizzle() {
+        let swizzle = [
+            swizzle[0] as GLint,
+            swizzle[1] as GLint,
+            swizzle[2] as GLint,
+            swizzle[3] as GLint,
+        ];
+        unsafe {
+            gl.TexParameteriv(target, gl::TEXTURE_SWIZZLE_RGBA, swizzle.as_ptr());
+        }
+    }
+}
+
+pub struct Texture {
+    gl: gl::Gl,
+    id: gl::types::GLuint,
+    width: u32,
+    height: u32,
+    layout: Option<PixelLayout>,
+}
+
+impl Texture {
+    /// Takes ownership of an already filled `TEXTURE_2D` texture object.
+    /// `layout` is the pixel layout `sub_image` expects, `None` if the
+    /// texture cannot be updated that way (e.g. compressed data).
+    pub(crate) fn from_raw(
+        gl: &gl::Gl,
+        id: GLuint,
+        width: u32,
+        height: u32,
+        layout: Option<PixelLayout>
+    ) -> Texture {
+        Texture {
             gl: gl.clone(),
-            id: texture_id,
-        })
+            id,
+            width,
+            height,
+            layout,
+        }
+    }
+
+    pub fn from_res(gl: &gl::Gl, res: &Resources, name: &str) -> Result<Texture, Error> {
+        TextureBuilder::new().from_res(gl, res, name)
+    }
+
+    /// Allocates immutable, uninitialized storage with linear filtering and
+    /// clamped edges, e.g. for a render target.
+    pub fn with_storage(gl: &gl::Gl, width: u32, height: u32, internal_format: GLenum) -> Texture {
+        let mut texture_id: GLuint = 0;
+        unsafe {
+            gl.GenTextures(1, &mut texture_id);
+            gl.BindTexture(gl::TEXTURE_2D, texture_id);
+        }
+
+        TextureBuilder::new().apply_parameters(gl, gl::TEXTURE_2D);
+
+        unsafe {
+            gl.TexStorage2D(gl::TEXTURE_2D, 1, internal_format, width as GLint, height as GLint);
+            gl.BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Texture::from_raw(gl, texture_id, width, height, layout_for_internal_format(internal_format))
     }
 
     pub fn bind(&self, slot: u32) {
@@ -49,11 +427,152 @@ impl Texture {
         }
     }
 
+    /// Binds the texture and the sampler to the same unit; the sampler's
+    /// state replaces the texture's own filtering and wrapping there.
+    pub fn bind_with_sampler(&self, slot: u32, sampler: &Sampler) {
+        self.bind(slot);
+        sampler.bind(slot);
+    }
+
     pub fn unbind(&self) {
         unsafe {
             self.gl.BindTexture(gl::TEXTURE_2D, 0);
         }
     }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Layout `sub_image` expects its pixels in: the one the texture was created from.
+    pub fn layout(&self) -> Option<PixelLayout> {
+        self.layout
+    }
+
+    /// Replaces the `w * h` rectangle at `x, y` (from the bottom-left) of mip
+    /// level 0. `pixels` are tightly packed rows in the layout the texture was
+    /// created from; mipmaps are not regenerated.
+    pub fn sub_image<T>(&self, x: u32, y: u32, w: u32, h: u32, pixels: &[T]) -> Result<(), Error> {
+        let layout = self.check_region(x, y, w, h)?;
+
+        let expected = w as usize * h as usize * layout.bytes_per_pixel;
+        let actual = pixels.len() * ::std::mem::size_of::<T>();
+        if actual != expected {
+            return Err(Error::SubImageSize { expected, actual });
+        }
+
+        unsafe {
+            self.write_region(x, y, w, h, layout, pixels.as_ptr() as *const gl::types::GLvoid);
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the rectangle at `x, y` with `img`, which must match the texture's layout.
+    pub fn sub_image_data(&self, x: u32, y: u32, img: &ImageData) -> Result<(), Error> {
+        let layout = self.check_region(x, y, img.width, img.height)?;
+        if img.layout() != layout {
+            return Err(Error::LayoutMismatch);
+        }
+
+        unsafe {
+            self.write_region(x, y, img.width, img.height, layout, img.as_ptr());
+        }
+
+        Ok(())
+    }
+
+    /// Reads mip level 0 back, top row first. 8-bit textures keep their
+    /// channels; everything else, including compressed data, is read as RGBA8.
+    pub fn to_image(&self) -> DynamicImage {
+        let (channels, bgr, layout) = match self.layout {
+            Some(layout) if layout.pixel_type == gl::UNSIGNED_BYTE => {
+                let bgr = layout.format == gl::BGR || layout.format == gl::BGRA;
+                (layout.bytes_per_pixel as u8, bgr, layout)
+            },
+            _ => (4, false, PixelLayout {
+                format: gl::RGBA,
+                pixel_type: gl::UNSIGNED_BYTE,
+                bytes_per_pixel: 4,
+            }),
+        };
+
+        let mut data = vec![0u8; self.width as usize * self.height as usize * layout.bytes_per_pixel];
+        unsafe {
+            self.gl.BindTexture(gl::TEXTURE_2D, self.id);
+            self.gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            self.gl.GetTexImage(
+                gl::TEXTURE_2D,
+                0,
+                layout.format,
+                layout.pixel_type,
+                data.as_mut_ptr() as *mut gl::types::GLvoid
+            );
+            self.gl.PixelStorei(gl::PACK_ALIGNMENT, 4);
+            self.gl.BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        let mut img = ImageData {
+            width: self.width,
+            height: self.height,
+            channels,
+            bgr,
+            data: PixelData::U8(data),
+        };
+        img.flip_vertically();
+        img.into_dynamic()
+    }
+
+    pub(crate) fn check_region(&self, x: u32, y: u32, w: u32, h: u32) -> Result<PixelLayout, Error> {
+        let layout = self.layout.ok_or(Error::NotUpdatable)?;
+
+        let past_width = x.checked_add(w).map_or(true, |right| right > self.width);
+        let past_height = y.checked_add(h).map_or(true, |bottom| bottom > self.height);
+        if past_width || past_height {
+            return Err(Error::RegionOutOfBounds {
+                x, y, width: w, height: h,
+                texture_width: self.width,
+                texture_height: self.height
+            });
+        }
+
+        Ok(layout)
+    }
+
+    /// `pixels` is a client pointer, or a byte offset into the bound pixel unpack buffer.
+    pub(crate) unsafe fn write_region(
+        &self,
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+        layout: PixelLayout,
+        pixels: *const gl::types::GLvoid
+    ) {
+        self.gl.BindTexture(gl::TEXTURE_2D, self.id);
+        self.gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        self.gl.TexSubImage2D(
+            gl::TEXTURE_2D,
+            0,
+            x as GLint,
+            y as GLint,
+            w as GLint,
+            h as GLint,
+            layout.format,
+            layout.pixel_type,
+            pixels
+        );
+        self.gl.PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        self.gl.BindTexture(gl::TEXTURE_2D, 0);
+    }
 }
 
 impl Drop for Texture {
@@ -62,4 +581,4 @@ impl Drop for Texture {
             self.gl.DeleteTextures(1, &mut self.id);
         }
     }
-}
\ No newline at end of file
+}
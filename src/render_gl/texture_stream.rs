@@ -0,0 +1,58 @@
+use gl;
+
+use render_gl::buffer::PixelUnpackBuffer;
+use render_gl::texture::{Error, Texture};
+
+/// Streams pixels into a texture every frame through a ring of pixel unpack
+/// buffers. The copy from buffer to texture happens on the GPU timeline, and
+/// each buffer is orphaned before it is written, so the CPU never waits for
+/// an upload from a previous frame to finish.
+pub struct TextureStream {
+    buffers: Vec<PixelUnpackBuffer>,
+    next: usize,
+}
+
+impl TextureStream {
+    /// Two or three buffers are enough for one upload per frame.
+    pub fn new(gl: &gl::Gl, buffer_count: usize) -> TextureStream {
+        TextureStream {
+            buffers: (0..buffer_count.max(1)).map(|_| PixelUnpackBuffer::new(gl)).collect(),
+            next: 0,
+        }
+    }
+
+    /// Replaces the whole texture with what `write` puts into the mapped buffer.
+    pub fn upload<F>(&mut self, texture: &Texture, write: F) -> Result<(), Error>
+        where F: FnOnce(&mut [u8])
+    {
+        let (w, h) = (texture.width(), texture.height());
+        self.upload_region(texture, 0, 0, w, h, write)
+    }
+
+    /// Replaces the `w * h` rectangle at `x, y`; `write` receives a buffer of
+    /// tightly packed rows in the texture's pixel layout.
+    pub fn upload_region<F>(&mut self, texture: &Texture, x: u32, y: u32, w: u32, h: u32, write: F) -> Result<(), Error>
+        where F: FnOnce(&mut [u8])
+    {
+        let layout = texture.check_region(x, y, w, h)?;
+        let size = w as usize * h as usize * layout.bytes_per_pixel;
+
+        let buffer = &self.buffers[self.next];
+        self.next = (self.next + 1) % self.buffers.len();
+
+        buffer.bind();
+        buffer.stream_draw_orphan(size);
+        if !buffer.map_write(size, write) {
+            buffer.unbind();
+            return Err(Error::StreamMapFailed);
+        }
+
+        // with a pixel unpack buffer bound, the pointer is an offset into it
+        unsafe {
+            texture.write_region(x, y, w, h, layout, ::std::ptr::null());
+        }
+        buffer.unbind();
+
+        Ok(())
+    }
+}
@@ -1,30 +1,186 @@
 use gl;
+use sdl2::video::Window;
 
+use render_gl::render_state::ScissorRect;
+
+/// How the viewport rectangle is placed inside the drawable area.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FitMode {
+    /// Covers the whole drawable, whatever its aspect ratio.
+    Stretch,
+    /// Largest rectangle with the aspect ratio of `width x height`, centered,
+    /// with bars on the sides (pillarbox) or top and bottom (letterbox).
+    Letterbox { width: u32, height: u32 },
+    /// Like `Letterbox`, but only whole multiples of `width x height`, so
+    /// every virtual pixel covers the same number of drawable pixels.
+    Integer { width: u32, height: u32 },
+}
+
+/// Area of the drawable (the framebuffer in pixels, which on HiDPI displays
+/// is larger than the window size in screen coordinates) that is rendered to.
+///
+/// `x, y, w, h` are drawable pixels from the bottom-left corner. Pixels
+/// outside the rectangle are left alone by `enable`, so clear the whole
+/// drawable before enabling a letterboxed viewport.
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Viewport {
     pub x: i32,
     pub y: i32,
     pub w: i32,
     pub h: i32,
+    drawable_w: i32,
+    drawable_h: i32,
+    fit: FitMode,
 }
 
 impl Viewport {
+    /// Stretched over a drawable of `w x h` pixels.
     pub fn for_window(w: i32, h: i32) -> Viewport {
         Viewport {
             x: 0,
             y: 0,
             w,
-            h
+            h,
+            drawable_w: w,
+            drawable_h: h,
+            fit: FitMode::Stretch,
         }
     }
 
+    /// Stretched over the drawable size of the window's GL context.
+    pub fn for_sdl_window(window: &Window) -> Viewport {
+        let (w, h) = window.drawable_size();
+        Viewport::for_window(w as i32, h as i32)
+    }
+
+    /// `w` and `h` are the new drawable size in pixels, not the window size
+    /// from `WindowEvent::Resized`; prefer `update_size_from`.
     pub fn update_size(&mut self, w: i32, h: i32) {
+        self.drawable_w = w;
+        self.drawable_h = h;
+        self.fit();
+    }
+
+    /// Call on `WindowEvent::SizeChanged`, and when the window moves to a
+    /// display with a different pixel density.
+    pub fn update_size_from(&mut self, window: &Window) {
+        let (w, h) = window.drawable_size();
+        self.update_size(w as i32, h as i32);
+    }
+
+    pub fn set_fit_mode(&mut self, fit: FitMode) {
+        self.fit = fit;
+        self.fit();
+    }
+
+    pub fn fit_mode(&self) -> FitMode {
+        self.fit
+    }
+
+    pub fn drawable_size(&self) -> (i32, i32) {
+        (self.drawable_w, self.drawable_h)
+    }
+
+    /// Resolution the scene is laid out at: the fixed size of `Letterbox`
+    /// and `Integer`, or the viewport size when stretched.
+    pub fn virtual_size(&self) -> (u32, u32) {
+        match self.fit {
+            FitMode::Stretch => (self.w.max(0) as u32, self.h.max(0) as u32),
+            FitMode::Letterbox { width, height } |
+            FitMode::Integer { width, height } => (width, height),
+        }
+    }
+
+    fn fit(&mut self) {
+        let (dw, dh) = (self.drawable_w, self.drawable_h);
+
+        let (w, h) = match self.fit {
+            FitMode::Stretch => (dw, dh),
+            FitMode::Letterbox { width, height } => {
+                let scale = (dw as f32 / width as f32).min(dh as f32 / height as f32);
+                ((width as f32 * scale).round() as i32, (height as f32 * scale).round() as i32)
+            },
+            FitMode::Integer { width, height } => {
+                let scale = (dw / width.max(1) as i32).min(dh / height.max(1) as i32).max(1);
+                (width as i32 * scale, height as i32 * scale)
+            },
+        };
+
+        self.x = (dw - w) / 2;
+        self.y = (dh - h) / 2;
         self.w = w;
         self.h = h;
     }
 
+    /// Part of this viewport given in fractions of its size, e.g.
+    /// `sub(0.0, 0.0, 0.5, 1.0)` for the left half of a split screen or
+    /// `sub(0.7, 0.7, 0.25, 0.25)` for a picture-in-picture in the top right.
+    pub fn sub(&self, x: f32, y: f32, w: f32, h: f32) -> Viewport {
+        let left = self.x + (self.w as f32 * x).round() as i32;
+        let bottom = self.y + (self.h as f32 * y).round() as i32;
+        let right = self.x + (self.w as f32 * (x + w)).round() as i32;
+        let top = self.y + (self.h as f32 * (y + h)).round() as i32;
+
+        Viewport {
+            x: left,
+            y: bottom,
+            w: right - left,
+            h: top - bottom,
+            drawable_w: self.drawable_w,
+            drawable_h: self.drawable_h,
+            fit: FitMode::Stretch,
+        }
+    }
+
+    /// `columns x rows` equal sub-viewports, row by row from the top left.
+    pub fn split(&self, columns: u32, rows: u32) -> Vec<Viewport> {
+        let (fw, fh) = (1.0 / columns.max(1) as f32, 1.0 / rows.max(1) as f32);
+
+        let mut views = Vec::with_capacity((columns * rows) as usize);
+        for row in 0..rows {
+            for column in 0..columns {
+                let y = 1.0 - (row + 1) as f32 * fh;
+                views.push(self.sub(column as f32 * fw, y, fw, fh));
+            }
+        }
+
+        views
+    }
+
+    /// Scissor rectangle matching this viewport, so clears of a sub-viewport
+    /// stay inside it.
+    pub fn scissor_rect(&self) -> ScissorRect {
+        ScissorRect {
+            x: self.x,
+            y: self.y,
+            w: self.w,
+            h: self.h,
+        }
+    }
+
+    /// Maps a point in drawable pixels from the top-left corner, as mouse
+    /// positions are reported once scaled to drawable size, to this viewport's
+    /// virtual resolution, also from the top-left corner. `None` if the point
+    /// is outside the viewport.
+    pub fn to_virtual(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        // viewport coordinates start at the bottom-left corner
+        let local_x = x - self.x as f32;
+        let local_y = (self.drawable_h as f32 - y) - self.y as f32;
+
+        if local_x < 0.0 || local_y < 0.0 || local_x >= self.w as f32 || local_y >= self.h as f32 {
+            return None;
+        }
+
+        let (vw, vh) = self.virtual_size();
+        Some((
+            local_x * vw as f32 / self.w as f32,
+            (self.h as f32 - local_y) * vh as f32 / self.h as f32,
+        ))
+    }
+
     pub fn enable(&self, gl: &gl::Gl) {
         unsafe {
             gl.Viewport(self.x, self.y, self.w, self.h);
         }
     }
-}
\ No newline at end of file
+}
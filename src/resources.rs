@@ -17,7 +17,7 @@ pub enum Error {
     UnableToLocateExe,
 
     #[fail(display = "Unable to load image")]
-    UnableToLoadImage(image::ImageError),
+    UnableToLoadImage(#[cause] image::ImageError),
 }
 
 impl From<io::Error> for Error {
@@ -44,6 +44,12 @@ impl Resources {
         })
     }
 
+    pub fn from_path(root_path: &Path) -> Resources {
+        Resources {
+            root_path: root_path.into()
+        }
+    }
+
     pub fn load_cstring(&self, resource_name: &str) -> Result<ffi::CString, Error> {
         let mut file = fs::File::open(
             resource_name_to_path(&self.root_path,resource_name)
@@ -62,9 +68,50 @@ impl Resources {
         Ok(unsafe { ffi::CString::from_vec_unchecked(buffer) })
     }
 
-    pub fn load_image(&self, resource_name: &str) -> Result<image::DynamicImage, image::ImageError> {
+    pub fn load_bytes(&self, resource_name: &str) -> Result<Vec<u8>, Error> {
+        let mut file = fs::File::open(
+            resource_name_to_path(&self.root_path,resource_name)
+        )?;
+
+        let mut buffer: Vec<u8> = Vec::with_capacity(
+            file.metadata()?.len() as usize
+        );
+        file.read_to_end(&mut buffer)?;
+
+        Ok(buffer)
+    }
+
+    /// Names of the files in a resource folder, sorted, as `folder/file`.
+    pub fn list_dir(&self, resource_name: &str) -> Result<Vec<String>, Error> {
+        let mut names = Vec::new();
+
+        for entry in fs::read_dir(resource_name_to_path(&self.root_path, resource_name))? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                names.push(format!(
+                    "{}/{}",
+                    resource_name.trim_right_matches('/'),
+                    entry.file_name().to_string_lossy()
+                ));
+            }
+        }
+
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn load_image(&self, resource_name: &str) -> Result<image::DynamicImage, Error> {
         let path = resource_name_to_path(&self.root_path,resource_name);
-        image::open(path)
+        image::open(path).map_err(Error::UnableToLoadImage)
+    }
+}
+
+/// Resource name of `file` in the folder of the resource `base`, for
+/// files that reference each other by relative path.
+pub fn relative_name(base: &str, file: &str) -> String {
+    match base.rfind('/') {
+        Some(i) => format!("{}/{}", &base[..i], file),
+        None => file.into(),
     }
 }
 
@@ -0,0 +1,154 @@
+use nalgebra as na;
+
+use render_gl::{Program, Viewport};
+use scene::transform::Transform;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Projection {
+    /// `fov_y` is the vertical field of view in radians.
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    /// `height` world units fit the viewport vertically; the width follows
+    /// from the aspect ratio.
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+/// Half-line from `origin`, e.g. from the camera through the mouse cursor.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: na::Point3<f32>,
+    /// Unit length.
+    pub direction: na::Vector3<f32>,
+}
+
+impl Ray {
+    pub fn at(&self, distance: f32) -> na::Point3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    /// Distance along the ray to the plane through `point` with `normal`;
+    /// `None` if the ray is parallel to it or points away.
+    pub fn intersect_plane(&self, point: &na::Point3<f32>, normal: &na::Vector3<f32>) -> Option<f32> {
+        let facing = self.direction.dot(normal);
+        if facing.abs() < 1e-6 {
+            return None;
+        }
+
+        let distance = (point - self.origin).dot(normal) / facing;
+        if distance >= 0.0 { Some(distance) } else { None }
+    }
+}
+
+/// Looks down the local -Z axis of `transform`, with +Y up; scale is
+/// ignored.
+///
+/// Methods that need the projection take the `Viewport` and use its
+/// virtual size as the aspect ratio, so the camera follows resizes and
+/// fit mode changes without being told.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub transform: Transform,
+    pub projection: Projection,
+}
+
+impl Camera {
+    pub fn new(projection: Projection) -> Camera {
+        Camera {
+            transform: Transform::identity(),
+            projection,
+        }
+    }
+
+    pub fn perspective(fov_y: f32, near: f32, far: f32) -> Camera {
+        Camera::new(Projection::Perspective { fov_y, near, far })
+    }
+
+    pub fn orthographic(height: f32, near: f32, far: f32) -> Camera {
+        Camera::new(Projection::Orthographic { height, near, far })
+    }
+
+    /// Width over height of the viewport's virtual size; 1 while it is empty.
+    pub fn aspect(viewport: &Viewport) -> f32 {
+        let (w, h) = viewport.virtual_size();
+        if w > 0 && h > 0 { w as f32 / h as f32 } else { 1.0 }
+    }
+
+    pub fn position(&self) -> na::Point3<f32> {
+        na::Point3::from(self.transform.translation)
+    }
+
+    /// Moves to `eye` and turns towards `target`.
+    pub fn look_at(&mut self, eye: &na::Point3<f32>, target: &na::Point3<f32>, up: &na::Vector3<f32>) {
+        self.transform = Transform {
+            scale: self.transform.scale,
+            ..Transform::look_at(eye, target, up)
+        };
+    }
+
+    pub fn view_matrix(&self) -> na::Matrix4<f32> {
+        self.transform.rotation.inverse().to_homogeneous()
+            * na::Matrix4::new_translation(&-self.transform.translation)
+    }
+
+    pub fn projection_matrix(&self, viewport: &Viewport) -> na::Matrix4<f32> {
+        let aspect = Camera::aspect(viewport);
+        match self.projection {
+            Projection::Perspective { fov_y, near, far } =>
+                na::Perspective3::new(aspect, fov_y, near, far).to_homogeneous(),
+            Projection::Orthographic { height, near, far } => {
+                let (half_w, half_h) = (height * aspect * 0.5, height * 0.5);
+                na::Orthographic3::new(-half_w, half_w, -half_h, half_h, near, far).to_homogeneous()
+            },
+        }
+    }
+
+    pub fn view_projection(&self, viewport: &Viewport) -> na::Matrix4<f32> {
+        self.projection_matrix(viewport) * self.view_matrix()
+    }
+
+    /// Sets the `View` and `Projection` uniforms of a bound program.
+    pub fn apply(&self, program: &Program, viewport: &Viewport) {
+        program.set_uniform_mat4f("View\0", &self.view_matrix());
+        program.set_uniform_mat4f("Projection\0", &self.projection_matrix(viewport));
+    }
+
+    /// World position of a point in normalized device coordinates, where
+    /// `z = -1` is on the near plane and `z = 1` on the far plane.
+    pub fn unproject(&self, ndc: &na::Point3<f32>, viewport: &Viewport) -> na::Point3<f32> {
+        let inverse = self.view_projection(viewport).try_inverse().unwrap_or_else(na::Matrix4::identity);
+        let h = inverse * na::Vector4::new(ndc.x, ndc.y, ndc.z, 1.0);
+        na::Point3::new(h.x / h.w, h.y / h.w, h.z / h.w)
+    }
+
+    /// Position of a world point in the viewport's virtual resolution, from
+    /// the top-left corner; `None` behind the camera.
+    pub fn world_to_screen(&self, point: &na::Point3<f32>, viewport: &Viewport) -> Option<(f32, f32)> {
+        let clip = self.view_projection(viewport) * point.to_homogeneous();
+        if clip.w <= 0.0 {
+            return None;
+        }
+
+        let (vw, vh) = viewport.virtual_size();
+        Some((
+            (clip.x / clip.w + 1.0) * 0.5 * vw as f32,
+            (1.0 - clip.y / clip.w) * 0.5 * vh as f32,
+        ))
+    }
+
+    /// Ray through a point in drawable pixels from the top-left corner,
+    /// e.g. the mouse position scaled to the drawable size. `None` if the
+    /// point is outside the viewport.
+    pub fn screen_ray(&self, x: f32, y: f32, viewport: &Viewport) -> Option<Ray> {
+        let (vx, vy) = viewport.to_virtual(x, y)?;
+        let (vw, vh) = viewport.virtual_size();
+        let ndc_x = vx / vw as f32 * 2.0 - 1.0;
+        let ndc_y = 1.0 - vy / vh as f32 * 2.0;
+
+        let near = self.unproject(&na::Point3::new(ndc_x, ndc_y, -1.0), viewport);
+        let far = self.unproject(&na::Point3::new(ndc_x, ndc_y, 1.0), viewport);
+
+        Some(Ray {
+            origin: near,
+            direction: (far - near).normalize(),
+        })
+    }
+}
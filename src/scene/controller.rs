@@ -0,0 +1,317 @@
+use nalgebra as na;
+
+use input::Input;
+use render_gl::Viewport;
+use scene::camera::{Camera, Projection};
+
+/// Fraction of the remaining way to the target to cover this frame. With
+/// `smoothing` in seconds the camera gets about 63% closer per `smoothing`
+/// seconds at any frame rate; 0 follows the input immediately.
+fn smoothing_factor(smoothing: f32, dt: f32) -> f32 {
+    if smoothing <= 0.0 {
+        1.0
+    } else {
+        1.0 - (-dt / smoothing).exp()
+    }
+}
+
+/// Turned by `yaw` about +Y, then tilted by `pitch` about the turned +X.
+fn yaw_pitch(yaw: f32, pitch: f32) -> na::UnitQuaternion<f32> {
+    na::UnitQuaternion::from_axis_angle(&na::Vector3::y_axis(), yaw)
+        * na::UnitQuaternion::from_axis_angle(&na::Vector3::x_axis(), pitch)
+}
+
+/// Inverse of `yaw_pitch` for a forward (-Z) direction.
+fn yaw_pitch_of(forward: &na::Vector3<f32>) -> (f32, f32) {
+    let forward = forward.normalize();
+    ((-forward.x).atan2(-forward.z), forward.y.max(-1.0).min(1.0).asin())
+}
+
+/// First-person free flight: WASD to move, E/Q or Space/Ctrl to rise and
+/// sink, Shift to go faster, right mouse button held to look around.
+pub struct FlyController {
+    /// World units per second.
+    pub speed: f32,
+    pub fast_multiplier: f32,
+    /// Radians per drawable pixel of mouse motion.
+    pub sensitivity: f32,
+    pub smoothing: f32,
+    /// Maximum angle above or below the horizon, in radians.
+    pub pitch_limit: f32,
+    pub invert_y: bool,
+    position: na::Vector3<f32>,
+    yaw: f32,
+    pitch: f32,
+    smoothed_position: na::Vector3<f32>,
+    smoothed_yaw: f32,
+    smoothed_pitch: f32,
+}
+
+impl FlyController {
+    /// Starts where `camera` is, looking where it looks; roll is dropped.
+    pub fn from_camera(camera: &Camera) -> FlyController {
+        let position = camera.transform.translation;
+        let (yaw, pitch) = yaw_pitch_of(&camera.transform.forward());
+
+        FlyController {
+            speed: 2.0,
+            fast_multiplier: 4.0,
+            sensitivity: 0.005,
+            smoothing: 0.05,
+            pitch_limit: 89f32.to_radians(),
+            invert_y: false,
+            position,
+            yaw,
+            pitch,
+            smoothed_position: position,
+            smoothed_yaw: yaw,
+            smoothed_pitch: pitch,
+        }
+    }
+
+    pub fn update(&mut self, camera: &mut Camera, input: &Input, dt: f32) {
+        if input.mouse_right {
+            let dy = if self.invert_y { -input.mouse_dy } else { input.mouse_dy };
+            self.yaw -= input.mouse_dx * self.sensitivity;
+            self.pitch = (self.pitch - dy * self.sensitivity)
+                .max(-self.pitch_limit)
+                .min(self.pitch_limit);
+        }
+
+        let rotation = yaw_pitch(self.yaw, self.pitch);
+        let movement = rotation * -na::Vector3::z() * (input.up - input.down) as f32
+            + rotation * na::Vector3::x() * (input.right - input.left) as f32
+            + na::Vector3::y() * (input.ascend - input.descend) as f32;
+
+        if movement.norm_squared() > 0.0 {
+            let speed = if input.fast { self.speed * self.fast_multiplier } else { self.speed };
+            self.position += movement.normalize() * speed * dt;
+        }
+
+        let k = smoothing_factor(self.smoothing, dt);
+        self.smoothed_position += (self.position - self.smoothed_position) * k;
+        self.smoothed_yaw += (self.yaw - self.smoothed_yaw) * k;
+        self.smoothed_pitch += (self.pitch - self.smoothed_pitch) * k;
+
+        camera.transform.translation = self.smoothed_position;
+        camera.transform.rotation = yaw_pitch(self.smoothed_yaw, self.smoothed_pitch);
+    }
+}
+
+/// Circles a target point: left mouse drag to orbit, middle drag to move
+/// the target, wheel to zoom in and out.
+pub struct OrbitController {
+    /// Radians per drawable pixel of mouse motion.
+    pub rotate_sensitivity: f32,
+    /// Fraction of the distance moved per drawable pixel when panning, so
+    /// panning feels the same close up and far away.
+    pub pan_sensitivity: f32,
+    /// Fraction of the distance covered per wheel notch.
+    pub zoom_speed: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Maximum angle above or below the target's horizon, in radians.
+    pub pitch_limit: f32,
+    pub smoothing: f32,
+    target: na::Vector3<f32>,
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+    smoothed_target: na::Vector3<f32>,
+    smoothed_distance: f32,
+    smoothed_yaw: f32,
+    smoothed_pitch: f32,
+}
+
+impl OrbitController {
+    pub fn new(target: &na::Point3<f32>, distance: f32) -> OrbitController {
+        OrbitController {
+            rotate_sensitivity: 0.01,
+            pan_sensitivity: 0.002,
+            zoom_speed: 0.1,
+            min_distance: 0.1,
+            max_distance: 1000.0,
+            pitch_limit: 89f32.to_radians(),
+            smoothing: 0.08,
+            target: target.coords,
+            distance,
+            yaw: 0.0,
+            pitch: 0.0,
+            smoothed_target: target.coords,
+            smoothed_distance: distance,
+            smoothed_yaw: 0.0,
+            smoothed_pitch: 0.0,
+        }
+    }
+
+    /// Orbits `target` from where `camera` is now.
+    pub fn from_camera(camera: &Camera, target: &na::Point3<f32>) -> OrbitController {
+        let offset = camera.transform.translation - target.coords;
+        let distance = offset.norm();
+        let mut orbit = OrbitController::new(target, distance);
+
+        if distance > 0.0 {
+            let (yaw, pitch) = yaw_pitch_of(&-offset);
+            orbit.yaw = yaw;
+            orbit.pitch = pitch;
+            orbit.smoothed_yaw = yaw;
+            orbit.smoothed_pitch = pitch;
+        }
+        orbit
+    }
+
+    pub fn target(&self) -> na::Point3<f32> {
+        na::Point3::from(self.target)
+    }
+
+    pub fn set_target(&mut self, target: &na::Point3<f32>) {
+        self.target = target.coords;
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance.max(self.min_distance).min(self.max_distance);
+    }
+
+    pub fn update(&mut self, camera: &mut Camera, input: &Input, dt: f32) {
+        if input.mouse_left {
+            self.yaw -= input.mouse_dx * self.rotate_sensitivity;
+            self.pitch = (self.pitch - input.mouse_dy * self.rotate_sensitivity)
+                .max(-self.pitch_limit)
+                .min(self.pitch_limit);
+        }
+
+        if input.mouse_middle {
+            let rotation = yaw_pitch(self.yaw, self.pitch);
+            let scale = self.pan_sensitivity * self.distance;
+            self.target += rotation * na::Vector3::x() * (-input.mouse_dx * scale)
+                + rotation * na::Vector3::y() * (input.mouse_dy * scale);
+        }
+
+        if input.wheel != 0.0 {
+            let distance = self.distance * (1.0 - self.zoom_speed).powf(input.wheel);
+            self.set_distance(distance);
+        }
+
+        let k = smoothing_factor(self.smoothing, dt);
+        self.smoothed_target += (self.target - self.smoothed_target) * k;
+        self.smoothed_distance += (self.distance - self.smoothed_distance) * k;
+        self.smoothed_yaw += (self.yaw - self.smoothed_yaw) * k;
+        self.smoothed_pitch += (self.pitch - self.smoothed_pitch) * k;
+
+        // looking down -Z from +Z * distance keeps the target centered
+        let rotation = yaw_pitch(self.smoothed_yaw, self.smoothed_pitch);
+        camera.transform.translation = self.smoothed_target + rotation * na::Vector3::z() * self.smoothed_distance;
+        camera.transform.rotation = rotation;
+    }
+}
+
+/// 2D view over the XY plane for an orthographic camera: WASD or left or
+/// middle mouse drag to pan, wheel to zoom towards the cursor.
+pub struct PanZoomController {
+    /// World units visible vertically at zoom 1.
+    pub height: f32,
+    /// Viewport heights per second when panning with the keyboard.
+    pub pan_speed: f32,
+    /// Zoom factor change per wheel notch.
+    pub zoom_speed: f32,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    pub smoothing: f32,
+    /// Lowest and highest allowed view center.
+    pub bounds: Option<(na::Vector2<f32>, na::Vector2<f32>)>,
+    center: na::Vector2<f32>,
+    zoom: f32,
+    smoothed_center: na::Vector2<f32>,
+    smoothed_zoom: f32,
+}
+
+impl PanZoomController {
+    pub fn new(center: na::Vector2<f32>, height: f32) -> PanZoomController {
+        PanZoomController {
+            height,
+            pan_speed: 1.0,
+            zoom_speed: 0.1,
+            min_zoom: 0.1,
+            max_zoom: 10.0,
+            smoothing: 0.05,
+            bounds: None,
+            center,
+            zoom: 1.0,
+            smoothed_center: center,
+            smoothed_zoom: 1.0,
+        }
+    }
+
+    pub fn center(&self) -> na::Vector2<f32> {
+        self.center
+    }
+
+    pub fn set_center(&mut self, center: na::Vector2<f32>) {
+        self.center = center;
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(self.min_zoom).min(self.max_zoom);
+    }
+
+    /// Also switches `camera` to an orthographic projection, keeping its
+    /// near and far planes.
+    pub fn update(&mut self, camera: &mut Camera, input: &Input, viewport: &Viewport, dt: f32) {
+        let pixels = viewport.h.max(1) as f32;
+        let units_per_pixel = self.height / self.zoom / pixels;
+
+        let keys = na::Vector2::new(
+            (input.right - input.left) as f32,
+            (input.up - input.down) as f32,
+        );
+        self.center += keys * self.pan_speed * pixels * units_per_pixel * dt;
+
+        if input.mouse_left || input.mouse_middle {
+            self.center += na::Vector2::new(-input.mouse_dx, input.mouse_dy) * units_per_pixel;
+        }
+
+        if input.wheel != 0.0 {
+            // keep the point under the cursor in place
+            let (_, drawable_h) = viewport.drawable_size();
+            let cursor = na::Vector2::new(
+                input.mouse_x - (viewport.x as f32 + viewport.w as f32 * 0.5),
+                (drawable_h as f32 - input.mouse_y) - (viewport.y as f32 + viewport.h as f32 * 0.5),
+            );
+            let under_cursor = self.center + cursor * units_per_pixel;
+
+            let zoom = self.zoom * (1.0 + self.zoom_speed).powf(input.wheel);
+            self.set_zoom(zoom);
+            self.center = under_cursor - cursor * (self.height / self.zoom / pixels);
+        }
+
+        if let Some((min, max)) = self.bounds {
+            self.center.x = self.center.x.max(min.x).min(max.x);
+            self.center.y = self.center.y.max(min.y).min(max.y);
+        }
+
+        let k = smoothing_factor(self.smoothing, dt);
+        self.smoothed_center += (self.center - self.smoothed_center) * k;
+        self.smoothed_zoom += (self.zoom - self.smoothed_zoom) * k;
+
+        let (near, far) = match camera.projection {
+            Projection::Perspective { near, far, .. } |
+            Projection::Orthographic { near, far, .. } => (near, far),
+        };
+        camera.projection = Projection::Orthographic {
+            height: self.height / self.smoothed_zoom,
+            near,
+            far,
+        };
+        camera.transform.translation.x = self.smoothed_center.x;
+        camera.transform.translation.y = self.smoothed_center.y;
+        camera.transform.rotation = na::UnitQuaternion::identity();
+    }
+}
@@ -0,0 +1,343 @@
+use nalgebra as na;
+use std::cell::Cell;
+
+use render_gl::Viewport;
+use scene::camera::Camera;
+use scene::transform::Transform;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Node {:?} cannot become a child of its own descendant {:?}", node, parent)]
+    Cycle {
+        node: NodeId,
+        parent: NodeId
+    },
+}
+
+/// Something drawn at a node, such as a `Shape`. `world` is the node's
+/// world matrix, which replaces or is combined with the object's own;
+/// `camera` provides the view and projection for `viewport`.
+pub trait Renderable {
+    fn draw(&self, world: &na::Matrix4<f32>, camera: &Camera, viewport: &Viewport);
+}
+
+/// Handle to a node of a `SceneGraph`. Removing a node invalidates its
+/// handle; the slot may be reused, but old handles never alias new nodes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: usize,
+    generation: u32,
+}
+
+struct Node {
+    name: String,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    local: Transform,
+    visible: bool,
+    renderable: Option<Box<dyn Renderable>>,
+    /// Cached world matrix, valid unless `dirty`. A dirty node always has
+    /// dirty descendants, so clean ancestors never hide a stale child.
+    world: Cell<na::Matrix4<f32>>,
+    dirty: Cell<bool>,
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<Node>,
+}
+
+/// Nodes with local transforms, stored in an arena and linked by `NodeId`.
+///
+/// World matrices are recomputed only when read after a node or one of
+/// its ancestors has moved. Methods panic when given a removed node's id,
+/// like indexing a `Vec` out of bounds; check with `contains` if unsure.
+pub struct SceneGraph {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    roots: Vec<NodeId>,
+}
+
+impl SceneGraph {
+    pub fn new() -> SceneGraph {
+        SceneGraph {
+            slots: Vec::new(),
+            free: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    /// Adds a root node with an identity transform.
+    pub fn add(&mut self, name: &str) -> NodeId {
+        let id = self.allocate(name, None);
+        self.roots.push(id);
+        id
+    }
+
+    /// Adds a node that moves with `parent`.
+    pub fn add_child(&mut self, parent: NodeId, name: &str) -> NodeId {
+        let id = self.allocate(name, Some(parent));
+        self.node_mut(parent).children.push(id);
+        id
+    }
+
+    fn allocate(&mut self, name: &str, parent: Option<NodeId>) -> NodeId {
+        let node = Node {
+            name: name.into(),
+            parent,
+            children: Vec::new(),
+            local: Transform::identity(),
+            visible: true,
+            renderable: None,
+            world: Cell::new(na::Matrix4::identity()),
+            dirty: Cell::new(true),
+        };
+
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.generation += 1;
+                slot.node = Some(node);
+                NodeId { index, generation: slot.generation }
+            },
+            None => {
+                self.slots.push(Slot { generation: 0, node: Some(node) });
+                NodeId { index: self.slots.len() - 1, generation: 0 }
+            },
+        }
+    }
+
+    /// Removes the node and everything attached to it.
+    pub fn remove(&mut self, id: NodeId) {
+        self.detach(id);
+
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = self.slots[id.index].node.take().expect("node removed twice");
+            stack.extend(node.children);
+            self.free.push(id.index);
+        }
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.slots.get(id.index)
+            .map(|slot| slot.generation == id.generation && slot.node.is_some())
+            .unwrap_or(false)
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// First node with the given name, searching depth first from the roots.
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        let mut stack = self.roots.iter().rev().cloned().collect::<Vec<_>>();
+        while let Some(id) = stack.pop() {
+            let node = self.node(id);
+            if node.name == name {
+                return Some(id);
+            }
+            stack.extend(node.children.iter().rev().cloned());
+        }
+        None
+    }
+
+    pub fn name(&self, id: NodeId) -> &str {
+        &self.node(id).name
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+
+    /// Attaches `id` to `parent`, or makes it a root with `None`. The local
+    /// transform is kept, so the node jumps to the same offset from its new
+    /// parent; use `set_parent_keep_world` to keep it in place.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> Result<(), Error> {
+        if let Some(parent) = parent {
+            if self.is_ancestor_or_self(id, parent) {
+                return Err(Error::Cycle { node: id, parent });
+            }
+        }
+
+        self.detach(id);
+        self.node_mut(id).parent = parent;
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+        self.mark_dirty(id);
+
+        Ok(())
+    }
+
+    /// Like `set_parent`, but adjusts the local transform so the node stays
+    /// where it is in the world, e.g. when a hand picks up a sword.
+    pub fn set_parent_keep_world(&mut self, id: NodeId, parent: Option<NodeId>) -> Result<(), Error> {
+        let world = self.world_transform(id);
+        self.set_parent(id, parent)?;
+
+        let parent_world = parent.map(|parent| self.world_transform(parent))
+            .unwrap_or_else(Transform::identity);
+        self.set_local(id, parent_world.inverse() * world);
+
+        Ok(())
+    }
+
+    fn is_ancestor_or_self(&self, ancestor: NodeId, mut id: NodeId) -> bool {
+        loop {
+            if id == ancestor {
+                return true;
+            }
+            match self.node(id).parent {
+                Some(parent) => id = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Unlinks the node from its parent or from the roots.
+    fn detach(&mut self, id: NodeId) {
+        match self.node(id).parent {
+            Some(parent) => self.node_mut(parent).children.retain(|&child| child != id),
+            None => self.roots.retain(|&root| root != id),
+        }
+    }
+
+    /// Transform relative to the parent, or to the world for roots.
+    pub fn local(&self, id: NodeId) -> Transform {
+        self.node(id).local
+    }
+
+    pub fn set_local(&mut self, id: NodeId, local: Transform) {
+        self.node_mut(id).local = local;
+        self.mark_dirty(id);
+    }
+
+    /// Changes the local transform in place, e.g.
+    /// `scene.update_local(id, |t| t.translate(&step))`.
+    pub fn update_local<F>(&mut self, id: NodeId, f: F) where F: FnOnce(&mut Transform) {
+        f(&mut self.node_mut(id).local);
+        self.mark_dirty(id);
+    }
+
+    fn mark_dirty(&self, id: NodeId) {
+        let node = self.node(id);
+        node.dirty.set(true);
+
+        let mut stack = node.children.clone();
+        while let Some(id) = stack.pop() {
+            let node = self.node(id);
+            // descendants of a dirty node are dirty already
+            if !node.dirty.get() {
+                node.dirty.set(true);
+                stack.extend(node.children.iter().cloned());
+            }
+        }
+    }
+
+    /// Parent's world matrix times the local transform, recomputed if the
+    /// node or an ancestor moved since it was last read.
+    pub fn world_matrix(&self, id: NodeId) -> na::Matrix4<f32> {
+        let node = self.node(id);
+        if !node.dirty.get() {
+            return node.world.get();
+        }
+
+        let parent_world = match node.parent {
+            Some(parent) => self.world_matrix(parent),
+            None => na::Matrix4::identity(),
+        };
+        self.update_world(node, &parent_world)
+    }
+
+    fn update_world(&self, node: &Node, parent_world: &na::Matrix4<f32>) -> na::Matrix4<f32> {
+        if node.dirty.get() {
+            node.world.set(parent_world * node.local.to_matrix());
+            node.dirty.set(false);
+        }
+        node.world.get()
+    }
+
+    /// Local transforms composed up to the root, without the cache; see
+    /// `Transform` for how non-uniform scale is approximated.
+    pub fn world_transform(&self, id: NodeId) -> Transform {
+        let node = self.node(id);
+        match node.parent {
+            Some(parent) => self.world_transform(parent) * node.local,
+            None => node.local,
+        }
+    }
+
+    pub fn world_position(&self, id: NodeId) -> na::Point3<f32> {
+        let world = self.world_matrix(id);
+        na::Point3::new(world[(0, 3)], world[(1, 3)], world[(2, 3)])
+    }
+
+    pub fn is_visible(&self, id: NodeId) -> bool {
+        self.node(id).visible
+    }
+
+    /// Hidden nodes are skipped by `render` together with their children.
+    pub fn set_visible(&mut self, id: NodeId, visible: bool) {
+        self.node_mut(id).visible = visible;
+    }
+
+    pub fn set_renderable(&mut self, id: NodeId, renderable: Box<dyn Renderable>) {
+        self.node_mut(id).renderable = Some(renderable);
+    }
+
+    pub fn take_renderable(&mut self, id: NodeId) -> Option<Box<dyn Renderable>> {
+        self.node_mut(id).renderable.take()
+    }
+
+    /// Draws every visible renderable, parents before children, refreshing
+    /// world matrices on the way down.
+    pub fn render(&self, camera: &Camera, viewport: &Viewport) {
+        let mut stack = self.roots.iter().rev()
+            .map(|&id| (id, na::Matrix4::identity()))
+            .collect::<Vec<_>>();
+
+        while let Some((id, parent_world)) = stack.pop() {
+            let node = self.node(id);
+            if !node.visible {
+                continue;
+            }
+
+            let world = self.update_world(node, &parent_world);
+            if let Some(ref renderable) = node.renderable {
+                renderable.draw(&world, camera, viewport);
+            }
+
+            stack.extend(node.children.iter().rev().map(|&child| (child, world)));
+        }
+    }
+
+    fn node(&self, id: NodeId) -> &Node {
+        let slot = &self.slots[id.index];
+        match slot.node {
+            Some(ref node) if slot.generation == id.generation => node,
+            _ => panic!("{:?} was removed from the scene graph", id),
+        }
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        let slot = &mut self.slots[id.index];
+        if slot.generation != id.generation {
+            panic!("{:?} was removed from the scene graph", id);
+        }
+        match slot.node {
+            Some(ref mut node) => node,
+            None => panic!("{:?} was removed from the scene graph", id),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+mod camera;
+mod controller;
+mod graph;
+mod transform;
+
+pub use self::camera::Camera;
+pub use self::controller::{FlyController, OrbitController, PanZoomController};
+pub use self::graph::{SceneGraph, Renderable};
+pub use self::transform::Transform;
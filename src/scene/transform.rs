@@ -0,0 +1,287 @@
+use nalgebra as na;
+use std::ops::Mul;
+
+/// Position, orientation and size of an object, applied as scale, then
+/// rotation, then translation.
+///
+/// Unlike a matrix that is multiplied into every frame, the parts stay
+/// exact and can be read back. Composition and `inverse` are exact for
+/// uniform scale; with non-uniform scale under a rotated parent the result
+/// is the closest transform without shear.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: na::Vector3<f32>,
+    pub rotation: na::UnitQuaternion<f32>,
+    pub scale: na::Vector3<f32>,
+}
+
+impl Transform {
+    pub fn new(
+        translation: na::Vector3<f32>,
+        rotation: na::UnitQuaternion<f32>,
+        scale: na::Vector3<f32>
+    ) -> Transform {
+        Transform { translation, rotation, scale }
+    }
+
+    pub fn identity() -> Transform {
+        Transform::new(na::Vector3::zeros(), na::UnitQuaternion::identity(), na::Vector3::from_element(1.0))
+    }
+
+    pub fn from_translation(translation: na::Vector3<f32>) -> Transform {
+        Transform { translation, ..Transform::identity() }
+    }
+
+    pub fn from_rotation(rotation: na::UnitQuaternion<f32>) -> Transform {
+        Transform { rotation, ..Transform::identity() }
+    }
+
+    pub fn from_scale(scale: f32) -> Transform {
+        Transform { scale: na::Vector3::from_element(scale), ..Transform::identity() }
+    }
+
+    /// Placed at `eye`, looking at `target` along its -Z axis, with +Y as
+    /// close to `up` as possible; the convention of cameras and glTF nodes.
+    pub fn look_at(eye: &na::Point3<f32>, target: &na::Point3<f32>, up: &na::Vector3<f32>) -> Transform {
+        let mut transform = Transform::from_translation(eye.coords);
+        transform.face(target, up);
+        transform
+    }
+
+    /// Turns in place to look at `target`, keeping translation and scale.
+    pub fn face(&mut self, target: &na::Point3<f32>, up: &na::Vector3<f32>) {
+        let direction = target.coords - self.translation;
+        if direction.norm_squared() > 0.0 {
+            self.rotation = na::UnitQuaternion::new_observer_frame(&-direction, up);
+        }
+    }
+
+    pub fn translate(&mut self, offset: &na::Vector3<f32>) {
+        self.translation += offset;
+    }
+
+    /// Rotates about the parent's axes; see `rotate_local` for the own axes.
+    pub fn rotate(&mut self, rotation: &na::UnitQuaternion<f32>) {
+        self.rotation = rotation * self.rotation;
+    }
+
+    pub fn rotate_local(&mut self, rotation: &na::UnitQuaternion<f32>) {
+        self.rotation = self.rotation * rotation;
+    }
+
+    /// Direction of the local -Z axis.
+    pub fn forward(&self) -> na::Vector3<f32> {
+        self.rotation * -na::Vector3::z()
+    }
+
+    pub fn right(&self) -> na::Vector3<f32> {
+        self.rotation * na::Vector3::x()
+    }
+
+    pub fn up(&self) -> na::Vector3<f32> {
+        self.rotation * na::Vector3::y()
+    }
+
+    pub fn transform_point(&self, point: &na::Point3<f32>) -> na::Point3<f32> {
+        na::Point3::from(self.rotation * point.coords.component_mul(&self.scale) + self.translation)
+    }
+
+    /// Applies rotation and scale, not translation.
+    pub fn transform_vector(&self, vector: &na::Vector3<f32>) -> na::Vector3<f32> {
+        self.rotation * vector.component_mul(&self.scale)
+    }
+
+    /// `child` placed relative to `self`, as a parent's transform combines
+    /// with its child's; same as `self * child`.
+    pub fn compose(&self, child: &Transform) -> Transform {
+        Transform {
+            translation: self.transform_point(&na::Point3::from(child.translation)).coords,
+            rotation: self.rotation * child.rotation,
+            scale: self.scale.component_mul(&child.scale),
+        }
+    }
+
+    /// Undoes this transform, so `t.compose(&t.inverse())` is the identity.
+    pub fn inverse(&self) -> Transform {
+        let scale = self.scale.map(|s| if s != 0.0 { 1.0 / s } else { 0.0 });
+        let rotation = self.rotation.inverse();
+
+        Transform {
+            translation: -(rotation * self.translation).component_mul(&scale),
+            rotation,
+            scale,
+        }
+    }
+
+    /// Linear interpolation of translation and scale with normalized linear
+    /// interpolation of the rotation, which is cheaper than `slerp` and
+    /// close to it for the small steps of smoothing.
+    pub fn lerp(&self, other: &Transform, t: f32) -> Transform {
+        Transform {
+            translation: self.translation + (other.translation - self.translation) * t,
+            rotation: self.rotation.nlerp(&self.nearest(&other.rotation), t),
+            scale: self.scale + (other.scale - self.scale) * t,
+        }
+    }
+
+    /// Like `lerp`, but turns at constant angular speed.
+    pub fn slerp(&self, other: &Transform, t: f32) -> Transform {
+        let target = self.nearest(&other.rotation);
+        Transform {
+            rotation: self.rotation.try_slerp(&target, t, 1e-6)
+                .unwrap_or_else(|| self.rotation.nlerp(&target, t)),
+            ..self.lerp(other, t)
+        }
+    }
+
+    /// `rotation` or its negation, whichever is closer to ours, so that
+    /// interpolating towards it takes the shorter way round.
+    fn nearest(&self, rotation: &na::UnitQuaternion<f32>) -> na::UnitQuaternion<f32> {
+        if self.rotation.coords.dot(&rotation.coords) < 0.0 {
+            na::UnitQuaternion::new_unchecked(-*rotation.quaternion())
+        } else {
+            *rotation
+        }
+    }
+
+    pub fn to_matrix(&self) -> na::Matrix4<f32> {
+        na::Matrix4::new_translation(&self.translation)
+            * self.rotation.to_homogeneous()
+            * na::Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::identity()
+    }
+}
+
+impl<'a> Mul<&'a Transform> for &'a Transform {
+    type Output = Transform;
+
+    fn mul(self, child: &'a Transform) -> Transform {
+        self.compose(child)
+    }
+}
+
+impl Mul<Transform> for Transform {
+    type Output = Transform;
+
+    fn mul(self, child: Transform) -> Transform {
+        self.compose(&child)
+    }
+}
+
+impl From<Transform> for na::Matrix4<f32> {
+    fn from(transform: Transform) -> Self {
+        transform.to_matrix()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra as na;
+
+    use super::Transform;
+
+    fn assert_matrix_eq(a: &na::Matrix4<f32>, b: &na::Matrix4<f32>) {
+        assert!((a - b).iter().all(|x| x.abs() < 1e-5), "{} != {}", a, b);
+    }
+
+    fn sample() -> Transform {
+        Transform::new(
+            na::Vector3::new(1.0, -2.0, 3.0),
+            na::UnitQuaternion::from_euler_angles(0.3, -1.1, 2.0),
+            na::Vector3::from_element(1.5),
+        )
+    }
+
+    fn other() -> Transform {
+        Transform::new(
+            na::Vector3::new(-0.5, 4.0, 0.25),
+            na::UnitQuaternion::from_euler_angles(-0.7, 0.2, 0.9),
+            na::Vector3::from_element(0.5),
+        )
+    }
+
+    #[test]
+    fn inverse_undoes_transform() {
+        let t = sample();
+
+        assert_matrix_eq(&(t * t.inverse()).to_matrix(), &na::Matrix4::identity());
+        assert_matrix_eq(&(t.inverse() * t).to_matrix(), &na::Matrix4::identity());
+        assert_matrix_eq(&t.inverse().to_matrix(), &t.to_matrix().try_inverse().unwrap());
+    }
+
+    #[test]
+    fn compose_matches_matrix_product() {
+        let (parent, child) = (sample(), other());
+
+        assert_matrix_eq(&parent.compose(&child).to_matrix(), &(parent.to_matrix() * child.to_matrix()));
+        assert_matrix_eq(&(&parent * &child).to_matrix(), &(parent.to_matrix() * child.to_matrix()));
+    }
+
+    #[test]
+    fn transform_point_matches_matrix() {
+        let t = sample();
+        let p = na::Point3::new(0.5, 1.0, -2.0);
+
+        let expected = t.to_matrix() * p.to_homogeneous();
+        let actual = t.transform_point(&p);
+        assert!((actual.coords - expected.xyz()).norm() < 1e-5);
+    }
+
+    #[test]
+    fn interpolation_endpoints() {
+        let (a, b) = (sample(), other());
+
+        assert_matrix_eq(&a.slerp(&b, 0.0).to_matrix(), &a.to_matrix());
+        assert_matrix_eq(&a.slerp(&b, 1.0).to_matrix(), &b.to_matrix());
+        assert_matrix_eq(&a.lerp(&b, 0.0).to_matrix(), &a.to_matrix());
+        assert_matrix_eq(&a.lerp(&b, 1.0).to_matrix(), &b.to_matrix());
+
+        // the same orientation stored as -q
+        let flipped = Transform {
+            rotation: na::UnitQuaternion::new_unchecked(-*a.rotation.quaternion()),
+            ..a
+        };
+        for &t in &[0.0, 0.5, 1.0] {
+            assert_matrix_eq(&a.slerp(&flipped, t).to_matrix(), &a.to_matrix());
+            assert_matrix_eq(&a.lerp(&flipped, t).to_matrix(), &a.to_matrix());
+        }
+    }
+
+    #[test]
+    fn slerp_halfway() {
+        let a = Transform::identity();
+        let b = Transform {
+            translation: na::Vector3::new(2.0, 0.0, 0.0),
+            rotation: na::UnitQuaternion::from_axis_angle(&na::Vector3::y_axis(), 1.0),
+            scale: na::Vector3::from_element(3.0),
+        };
+
+        let half = a.slerp(&b, 0.5);
+        assert!((half.translation - na::Vector3::new(1.0, 0.0, 0.0)).norm() < 1e-5);
+        assert!((half.rotation.angle() - 0.5).abs() < 1e-5);
+        assert!((half.scale - na::Vector3::from_element(2.0)).norm() < 1e-5);
+
+        // a quarter turn stored as -q is still reached the short way
+        let quarter = na::UnitQuaternion::from_axis_angle(&na::Vector3::y_axis(), std::f32::consts::FRAC_PI_2);
+        let b = Transform {
+            rotation: na::UnitQuaternion::new_unchecked(-*quarter.quaternion()),
+            ..Transform::identity()
+        };
+        for half in &[a.slerp(&b, 0.5), a.lerp(&b, 0.5)] {
+            assert!((half.rotation.angle() - std::f32::consts::FRAC_PI_4).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn look_at_faces_target() {
+        let t = Transform::look_at(&na::Point3::new(0.0, 0.0, 5.0), &na::Point3::new(0.0, 0.0, 0.0), &na::Vector3::y());
+
+        assert!((t.forward() - -na::Vector3::z()).norm() < 1e-5);
+        assert!((t.up() - na::Vector3::y()).norm() < 1e-5);
+    }
+}
@@ -0,0 +1,199 @@
+//! Just enough of EGL to get a GL context without a window. libEGL is
+//! opened at run time, so machines without it still build and link the
+//! tests and simply skip the ones that need a context.
+
+use gl;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+
+type EGLDisplay = *mut c_void;
+type EGLConfig = *mut c_void;
+type EGLContext = *mut c_void;
+type EGLSurface = *mut c_void;
+type EGLint = i32;
+type EGLBoolean = u32;
+type EGLenum = u32;
+
+const EGL_NONE: EGLint = 0x3038;
+const EGL_RENDERABLE_TYPE: EGLint = 0x3040;
+const EGL_SURFACE_TYPE: EGLint = 0x3033;
+const EGL_PBUFFER_BIT: EGLint = 0x0001;
+const EGL_OPENGL_BIT: EGLint = 0x0008;
+const EGL_OPENGL_API: EGLenum = 0x30A2;
+const EGL_CONTEXT_MAJOR_VERSION: EGLint = 0x3098;
+const EGL_CONTEXT_MINOR_VERSION: EGLint = 0x30FB;
+const EGL_CONTEXT_OPENGL_PROFILE_MASK: EGLint = 0x30FD;
+const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: EGLint = 0x0001;
+const EGL_PLATFORM_SURFACELESS_MESA: EGLenum = 0x31DD;
+
+const RTLD_NOW: c_int = 2;
+
+#[link(name = "dl")]
+extern "C" {
+    fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    fn dlclose(handle: *mut c_void) -> c_int;
+}
+
+struct Egl {
+    get_proc_address: extern "C" fn(*const c_char) -> *mut c_void,
+    get_display: extern "C" fn(*mut c_void) -> EGLDisplay,
+    initialize: extern "C" fn(EGLDisplay, *mut EGLint, *mut EGLint) -> EGLBoolean,
+    terminate: extern "C" fn(EGLDisplay) -> EGLBoolean,
+    bind_api: extern "C" fn(EGLenum) -> EGLBoolean,
+    choose_config: extern "C" fn(EGLDisplay, *const EGLint, *mut EGLConfig, EGLint, *mut EGLint) -> EGLBoolean,
+    create_context: extern "C" fn(EGLDisplay, EGLConfig, EGLContext, *const EGLint) -> EGLContext,
+    destroy_context: extern "C" fn(EGLDisplay, EGLContext) -> EGLBoolean,
+    make_current: extern "C" fn(EGLDisplay, EGLSurface, EGLSurface, EGLContext) -> EGLBoolean,
+}
+
+/// Surfaceless GL 4.5 core context, current on the creating thread until dropped.
+/// Every test runs on its own thread, so each one creates its own context.
+pub struct HeadlessContext {
+    library: *mut c_void,
+    egl: Egl,
+    display: EGLDisplay,
+    context: EGLContext,
+    pub gl: gl::Gl,
+}
+
+impl HeadlessContext {
+    /// Fails with a reason when EGL, the surfaceless platform or a 4.5 core
+    /// context is unavailable.
+    pub fn new() -> Result<HeadlessContext, String> {
+        unsafe {
+            let library = ["libEGL.so.1", "libEGL.so"].iter()
+                .map(|name| dlopen(CString::new(*name).unwrap().as_ptr(), RTLD_NOW))
+                .find(|handle| !handle.is_null())
+                .ok_or_else(|| "libEGL not found".to_string())?;
+
+            let egl = match load_egl(library) {
+                Some(egl) => egl,
+                None => {
+                    dlclose(library);
+                    return Err("libEGL is missing core entry points".into());
+                },
+            };
+
+            match create_context(&egl) {
+                Ok((display, context)) => {
+                    let gl = gl::Gl::load_with(|name| {
+                        let name = CString::new(name).unwrap();
+                        (egl.get_proc_address)(name.as_ptr()) as *const gl::types::GLvoid
+                    });
+
+                    Ok(HeadlessContext {
+                        library,
+                        egl,
+                        display,
+                        context,
+                        gl,
+                    })
+                },
+                Err(message) => {
+                    dlclose(library);
+                    Err(message)
+                },
+            }
+        }
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        let none = ::std::ptr::null_mut();
+        (self.egl.make_current)(self.display, none, none, none);
+        (self.egl.destroy_context)(self.display, self.context);
+        (self.egl.terminate)(self.display);
+        unsafe {
+            dlclose(self.library);
+        }
+    }
+}
+
+unsafe fn load_egl(library: *mut c_void) -> Option<Egl> {
+    macro_rules! symbol {
+        ($name:expr) => {{
+            let name = CStr::from_bytes_with_nul_unchecked($name);
+            let ptr = dlsym(library, name.as_ptr());
+            if ptr.is_null() {
+                return None;
+            }
+            ::std::mem::transmute(ptr)
+        }}
+    }
+
+    Some(Egl {
+        get_proc_address: symbol!(b"eglGetProcAddress\0"),
+        get_display: symbol!(b"eglGetDisplay\0"),
+        initialize: symbol!(b"eglInitialize\0"),
+        terminate: symbol!(b"eglTerminate\0"),
+        bind_api: symbol!(b"eglBindAPI\0"),
+        choose_config: symbol!(b"eglChooseConfig\0"),
+        create_context: symbol!(b"eglCreateContext\0"),
+        destroy_context: symbol!(b"eglDestroyContext\0"),
+        make_current: symbol!(b"eglMakeCurrent\0"),
+    })
+}
+
+unsafe fn create_context(egl: &Egl) -> Result<(EGLDisplay, EGLContext), String> {
+    let none = ::std::ptr::null_mut();
+
+    // prefer the surfaceless platform; the default display may need X or Wayland
+    let get_platform_display = (egl.get_proc_address)(
+        CStr::from_bytes_with_nul_unchecked(b"eglGetPlatformDisplayEXT\0").as_ptr()
+    );
+    let mut display = none;
+    if !get_platform_display.is_null() {
+        let get_platform_display: extern "C" fn(EGLenum, *mut c_void, *const EGLint) -> EGLDisplay =
+            ::std::mem::transmute(get_platform_display);
+        display = get_platform_display(EGL_PLATFORM_SURFACELESS_MESA, none, ::std::ptr::null());
+    }
+    if display.is_null() {
+        display = (egl.get_display)(none);
+    }
+    if display.is_null() {
+        return Err("no EGL display".into());
+    }
+
+    let (mut major, mut minor) = (0, 0);
+    if (egl.initialize)(display, &mut major, &mut minor) == 0 {
+        return Err("eglInitialize failed".into());
+    }
+
+    let fail = |message: &str| {
+        (egl.terminate)(display);
+        Err(message.to_string())
+    };
+
+    if (egl.bind_api)(EGL_OPENGL_API) == 0 {
+        return fail("desktop OpenGL is not supported by EGL");
+    }
+
+    let config_attribs = [EGL_RENDERABLE_TYPE, EGL_OPENGL_BIT, EGL_SURFACE_TYPE, EGL_PBUFFER_BIT, EGL_NONE];
+    let mut config = none;
+    let mut config_count = 0;
+    if (egl.choose_config)(display, config_attribs.as_ptr(), &mut config, 1, &mut config_count) == 0
+        || config_count == 0
+    {
+        return fail("no EGL config renders desktop OpenGL");
+    }
+
+    let context_attribs = [
+        EGL_CONTEXT_MAJOR_VERSION, 4,
+        EGL_CONTEXT_MINOR_VERSION, 5,
+        EGL_CONTEXT_OPENGL_PROFILE_MASK, EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+        EGL_NONE,
+    ];
+    let context = (egl.create_context)(display, config, none, context_attribs.as_ptr());
+    if context.is_null() {
+        return fail("unable to create a GL 4.5 core context");
+    }
+
+    if (egl.make_current)(display, none, none, context) == 0 {
+        (egl.destroy_context)(display, context);
+        return fail("surfaceless contexts are not supported");
+    }
+
+    Ok((display, context))
+}
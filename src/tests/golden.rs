@@ -0,0 +1,150 @@
+//! Compares rendered images against reference PNGs in `tests/golden`.
+//!
+//! Rendering tests need a headless EGL context and are `#[ignore]`d, so run
+//! them with `cargo test -- --ignored`. References are only written when
+//! `GOLDEN_UPDATE=1` is set; a missing reference fails the test, and a
+//! mismatch fails it and leaves `<name>.actual.png` and `<name>.diff.png`
+//! in `target/golden`, with differing pixels in red over a faded copy of
+//! the reference.
+
+use image::{self, DynamicImage, Rgba, RgbaImage};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use render_gl::{ColorBuffer, Framebuffer, FramebufferBuilder};
+use resources::Resources;
+use tests::egl::HeadlessContext;
+
+/// Largest per-channel difference a pixel may have and still match, which
+/// absorbs rounding differences between drivers.
+pub const DEFAULT_TOLERANCE: u8 = 2;
+
+// fields drop in order, so the context has to outlive the framebuffer
+pub struct Golden {
+    pub target: Framebuffer,
+    pub res: Resources,
+    pub context: HeadlessContext,
+}
+
+impl Golden {
+    /// Panics when no headless context can be created.
+    pub fn new(name: &str, width: u32, height: u32) -> Golden {
+        let context = HeadlessContext::new()
+            .unwrap_or_else(|reason| panic!("golden test {} needs a headless EGL context: {}", name, reason));
+
+        let target = FramebufferBuilder::new(width, height)
+            .color_texture(::gl::RGBA8)
+            .build(&context.gl)
+            .expect("golden test framebuffer");
+        target.bind();
+
+        Golden {
+            target,
+            res: Resources::from_path(&manifest_dir().join("assets")),
+            context,
+        }
+    }
+
+    pub fn clear(&self, color: &ColorBuffer) {
+        color.enable(&self.context.gl);
+        color.clear(&self.context.gl);
+    }
+
+    /// Reads the target back and compares it against `tests/golden/<name>.png`.
+    pub fn check(&self, name: &str, tolerance: u8) {
+        let actual = self.target.to_image(0).to_rgba();
+        let reference_path = manifest_dir().join("tests").join("golden").join(format!("{}.png", name));
+
+        let update = env::var("GOLDEN_UPDATE").map(|v| v == "1").unwrap_or(false);
+        if update {
+            fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+            DynamicImage::ImageRgba8(actual).save(&reference_path).unwrap();
+            println!("wrote reference {}", reference_path.display());
+            return;
+        }
+
+        if !reference_path.exists() {
+            panic!(
+                "missing reference {}, render it with GOLDEN_UPDATE=1 cargo test -- --ignored and commit it",
+                reference_path.display()
+            );
+        }
+
+        let reference = image::open(&reference_path)
+            .unwrap_or_else(|e| panic!("unable to read {}: {}", reference_path.display(), e))
+            .to_rgba();
+
+        if let Some(diff) = compare(&reference, &actual, tolerance) {
+            let out_dir = manifest_dir().join("target").join("golden");
+            fs::create_dir_all(&out_dir).unwrap();
+
+            let actual_path = out_dir.join(format!("{}.actual.png", name));
+            let diff_path = out_dir.join(format!("{}.diff.png", name));
+            DynamicImage::ImageRgba8(actual).save(&actual_path).unwrap();
+            DynamicImage::ImageRgba8(diff.image).save(&diff_path).unwrap();
+
+            panic!(
+                "{} differs from its reference in {} pixels (max channel difference {}), see {}",
+                name,
+                diff.pixels,
+                diff.max_difference,
+                diff_path.display()
+            );
+        }
+    }
+}
+
+pub struct Difference {
+    pub pixels: usize,
+    pub max_difference: u8,
+    pub image: RgbaImage,
+}
+
+/// `None` if every pixel is within `tolerance` on every channel.
+pub fn compare(reference: &RgbaImage, actual: &RgbaImage, tolerance: u8) -> Option<Difference> {
+    if reference.dimensions() != actual.dimensions() {
+        let (w, h) = actual.dimensions();
+        return Some(Difference {
+            pixels: (w * h) as usize,
+            max_difference: 255,
+            image: RgbaImage::from_pixel(w, h, Rgba([255, 0, 0, 255])),
+        });
+    }
+
+    let mut pixels = 0;
+    let mut max_difference = 0;
+    let mut image = RgbaImage::new(actual.width(), actual.height());
+
+    for (x, y, expected) in reference.enumerate_pixels() {
+        let got = actual.get_pixel(x, y);
+        let difference = expected.data.iter().zip(got.data.iter())
+            .map(|(&a, &b)| (a as i16 - b as i16).abs() as u8)
+            .max()
+            .unwrap_or(0);
+
+        max_difference = max_difference.max(difference);
+        let out = if difference > tolerance {
+            pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let faded = |c: u8| 128 + c / 4;
+            Rgba([faded(expected.data[0]), faded(expected.data[1]), faded(expected.data[2]), 255])
+        };
+        image.put_pixel(x, y, out);
+    }
+
+    if pixels == 0 {
+        return None;
+    }
+
+    Some(Difference {
+        pixels,
+        max_difference,
+        image,
+    })
+}
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
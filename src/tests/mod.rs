@@ -0,0 +1,81 @@
+mod egl;
+mod golden;
+
+use image::{Rgba, RgbaImage};
+use nalgebra as na;
+
+use geometry::Shape;
+use render_gl::ColorBuffer;
+use self::golden::{compare, Golden, DEFAULT_TOLERANCE};
+
+fn background() -> ColorBuffer {
+    ColorBuffer::from_color(na::Vector3::new(0.3, 0.3, 0.5))
+}
+
+#[test]
+#[ignore]
+fn square() {
+    let golden = Golden::new("square", 64, 64);
+    let gl = &golden.context.gl;
+
+    let square = Shape::square(&golden.res, gl).unwrap();
+    golden.clear(&background());
+    square.render();
+
+    golden.check("square", DEFAULT_TOLERANCE);
+}
+
+#[test]
+#[ignore]
+fn square_moved() {
+    let golden = Golden::new("square_moved", 64, 64);
+    let gl = &golden.context.gl;
+
+    let mut square = Shape::square(&golden.res, gl).unwrap();
+    square.update_pos(&na::Vector3::new(0.25, -0.25, 0.0));
+    golden.clear(&background());
+    square.render();
+
+    golden.check("square_moved", DEFAULT_TOLERANCE);
+}
+
+#[test]
+#[ignore]
+fn triangle() {
+    let golden = Golden::new("triangle", 64, 64);
+    let gl = &golden.context.gl;
+
+    let triangle = Shape::triangle(&golden.res, gl).unwrap();
+    golden.clear(&background());
+    triangle.render();
+
+    golden.check("triangle", DEFAULT_TOLERANCE);
+}
+
+#[test]
+fn compare_within_tolerance() {
+    let reference = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+    let actual = RgbaImage::from_pixel(4, 4, Rgba([102, 99, 100, 255]));
+
+    assert!(compare(&reference, &actual, 2).is_none());
+}
+
+#[test]
+fn compare_reports_differing_pixels() {
+    let reference = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+    let mut actual = reference.clone();
+    actual.put_pixel(1, 2, Rgba([100, 140, 100, 255]));
+
+    let diff = compare(&reference, &actual, 2).unwrap();
+    assert_eq!(diff.pixels, 1);
+    assert_eq!(diff.max_difference, 40);
+    assert_eq!(*diff.image.get_pixel(1, 2), Rgba([255, 0, 0, 255]));
+}
+
+#[test]
+fn compare_size_mismatch() {
+    let reference = RgbaImage::new(4, 4);
+    let actual = RgbaImage::new(4, 2);
+
+    assert_eq!(compare(&reference, &actual, 255).unwrap().pixels, 8);
+}